@@ -1,16 +1,17 @@
-use cosmwasm_std::{Addr, Binary, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env, MessageInfo, QueryRequest, Reply, ReplyOn, Response, StdError, StdResult, SubMsg, WasmMsg, WasmQuery, attr, entry_point, from_binary, to_binary};
+use cosmwasm_std::{Addr, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, QueryRequest, Reply, ReplyOn, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg, WasmQuery, attr, entry_point, from_binary, to_binary};
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use protobuf::Message;
 use terraswap::asset::{AssetInfo};
 use terraswap::token::InstantiateMsg as TokenInstantiateMsg;
 use cw20::{MinterResponse, Cw20ReceiveMsg};
-use seesaw::bank::{BorrowRateResponse, ConfigResponse, Cw20HookMsg, Direction, ExecuteMsg, FundingResponse, InstantiateMsg, MarketResponse, PositionResponse, QueryMsg, Sign, StateResponse};
+use seesaw::number::SignedDecimal256;
+use seesaw::bank::{BorrowRateResponse, ConfigResponse, Cw20HookMsg, Direction, ExecuteMsg, FundingResponse, FundingScheduleResponse, InstantiateMsg, MarketResponse, PositionResponse, QueryMsg, Sign, StateResponse};
 use seesaw::vamm::{ExecuteMsg as VammExecuteMsg, QueryMsg as VammQueryMsg, StateResponse as VammStateResponse};
 
 use crate::error::ContractError;
 use crate::state::{ CONFIG, Config, POSITIONS, Position, STATE, State, MARKETS, Market };
 use crate::response::MsgInstantiateContractResponse;
-use crate::positions::{add_margin, close_position, liquidate, open_position, simulate_close};
+use crate::positions::{add_margin, close_position, liquidate, open_position, settle_position, simulate_close};
 
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
@@ -26,12 +27,15 @@ pub fn instantiate(
         owner_addr: deps.api.addr_canonicalize(&info.sender.as_str())?,
         stable_denom: msg.stable_denom,
         liquidation_ratio: msg.liquidation_ratio,
-        liquidation_reward: msg.liquidation_reward
+        liquidation_reward: msg.liquidation_reward,
+        fee_recipient: deps.api.addr_canonicalize(&msg.fee_recipient)?,
+        funding_bounty: msg.funding_bounty
     };
     CONFIG.save(deps.storage, &config)?;
 
     let state = State {
-        last_cumulative_funding_fee: env.block.height
+        last_cumulative_funding_fee: env.block.height,
+        fee_pool: Uint256::zero()
     };
 
     STATE.save(deps.storage, &state)?;
@@ -58,19 +62,24 @@ pub fn execute(
             let valid_addr: Addr = deps.api.addr_validate(&contract_addr.as_str())?;
             register_market(deps, env, info, valid_addr)
         },
-        ExecuteMsg::OpenPosition { market_addr, open_value, direction  } => {
+        ExecuteMsg::OpenPosition { market_addr, open_value, direction, base_amount_limit, belief_price, max_spread } => {
             let valid_addr: Addr = deps.api.addr_validate(&market_addr.as_str())?;
-            open_position(deps, env, info, valid_addr, direction, open_value)
+            open_position(deps, env, info, valid_addr, direction, open_value, base_amount_limit, belief_price, max_spread)
         },
-        ExecuteMsg::ClosePosition { market_addr } => {
+        ExecuteMsg::ClosePosition { market_addr, quote_amount_limit, belief_price, max_spread } => {
             let valid_addr: Addr = deps.api.addr_validate(&market_addr.as_str())?;
-            close_position(deps, env, info, valid_addr)
+            close_position(deps, env, info, valid_addr, quote_amount_limit, belief_price, max_spread)
         },
         ExecuteMsg::Liquidate { market_addr, holder_addr } => {
             let valid_market_addr: Addr = deps.api.addr_validate(&market_addr.as_str())?;
             let valid_holder_addr: Addr = deps.api.addr_validate(&holder_addr.as_str())?;
             liquidate(deps, env, info, valid_market_addr, valid_holder_addr )
         },
+        ExecuteMsg::SettlePosition { market_addr } => {
+            let valid_addr: Addr = deps.api.addr_validate(&market_addr.as_str())?;
+            settle_position(deps, env, info, valid_addr)
+        },
+        ExecuteMsg::FundFeePool {} => fund_fee_pool(deps, env, info),
         ExecuteMsg::UpdateFunding { market_addr } => {
             let valid_addr: Addr = deps.api.addr_validate(&market_addr.as_str())?;
             update_funding(deps, env, info, valid_addr)
@@ -84,11 +93,27 @@ pub fn execute(
 
 pub fn update_funding(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    env: Env,
+    info: MessageInfo,
     market_addr: Addr,
 ) -> Result<Response, ContractError> {
 
+    // Only registered markets pay out bounties
+    let mut market: Market = MARKETS.load(deps.storage, market_addr.as_bytes())?;
+
+    let market_state: VammStateResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: market_addr.to_string(),
+        msg: to_binary(&VammQueryMsg::State { })?,
+    }))?;
+
+    // Each settlement pays a single bounty
+    if env.block.time.seconds() < market_state.next_funding_time || market_state.next_funding_time <= market.last_funding_time {
+        return Err(ContractError::NotTime {});
+    }
+
+    market.last_funding_time = market_state.next_funding_time;
+    MARKETS.save(deps.storage, market_addr.as_bytes(), &market)?;
+
     let mut messages: Vec<CosmosMsg> = vec![];
 
     /// 1. Prompt update to cumulative funding fraction.
@@ -102,14 +127,75 @@ pub fn update_funding(
 
     /// 2. Save funding fraction.
     let msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: _env.contract.address.to_string(),
+        contract_addr: env.contract.address.to_string(),
         msg: to_binary(&ExecuteMsg::UpdateFundingInternal { market_addr: market_addr.to_string() })?,
         funds: vec![],
     });
 
     messages.push(msg);
 
-    Ok(Response::new().add_messages(messages))
+    /// 3. Pay the keeper, the whole call reverts if the settlement fails
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut state: State = STATE.load(deps.storage)?;
+
+    let bounty = funding_bounty(&config, &state);
+    if bounty > Uint256::zero() {
+        state.fee_pool = state.fee_pool - bounty;
+        STATE.save(deps.storage, &state)?;
+
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: config.stable_denom,
+                amount: Uint128::from(bounty),
+            }],
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", "update_funding"),
+            ("bounty", bounty.to_string().as_str())
+        ])
+    )
+}
+
+// Bounty for the next funding settlement, no more than what is left in the pool
+fn funding_bounty(config: &Config, state: &State) -> Uint256 {
+    if config.funding_bounty > state.fee_pool {
+        state.fee_pool
+    } else {
+        config.funding_bounty
+    }
+}
+
+pub fn fund_fee_pool(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let amount: Uint256 = info
+        .funds
+        .iter()
+        .find(|c| c.denom == config.stable_denom)
+        .map(|c| Uint256::from(c.amount))
+        .unwrap_or_else(Uint256::zero);
+
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    let mut state: State = STATE.load(deps.storage)?;
+    state.fee_pool += amount;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "fund_fee_pool"),
+        ("amount", amount.to_string().as_str())
+    ]))
 }
 
 pub fn update_funding_internal(
@@ -171,7 +257,8 @@ pub fn register_market(
 
     let market = Market {
         contract_addr: deps.api.addr_canonicalize(contract_addr.as_str())?,
-        cumulative_funding_premium: market_state.funding_premium_cumulative
+        cumulative_funding_premium: market_state.funding_premium_cumulative,
+        last_funding_time: 0
     };
 
     MARKETS.save(deps.storage, key, &market)?;
@@ -193,6 +280,10 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let valid_user_addr = deps.api.addr_validate(&user_addr.as_str())?;
 
             to_binary(&query_position(deps, valid_market_addr, valid_user_addr)?)
+        },
+        QueryMsg::FundingSchedule { market_addr } => {
+            let valid_addr = deps.api.addr_validate(&market_addr.as_str())?;
+            to_binary(&query_funding_schedule(deps, valid_addr)?)
         }
     }
 }
@@ -203,6 +294,8 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         contract_addr: deps.api.addr_humanize(&config.contract_addr)?,
         owner_addr: deps.api.addr_humanize(&config.owner_addr)?,
         stable_denom: config.stable_denom,
+        fee_recipient: deps.api.addr_humanize(&config.fee_recipient)?,
+        funding_bounty: config.funding_bounty,
     })
 }
 
@@ -210,6 +303,24 @@ fn query_state(deps: Deps) -> StdResult<StateResponse> {
     let state = STATE.load(deps.storage)?;
     Ok(StateResponse {
         last_cumulative_funding_fee: state.last_cumulative_funding_fee,
+        fee_pool: state.fee_pool,
+    })
+}
+
+fn query_funding_schedule(deps: Deps, market_addr: Addr) -> StdResult<FundingScheduleResponse> {
+    MARKETS.load(deps.storage, market_addr.as_bytes())?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    let market_state: VammStateResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: market_addr.to_string(),
+        msg: to_binary(&VammQueryMsg::State { })?,
+    }))?;
+
+    Ok(FundingScheduleResponse {
+        next_funding_time: market_state.next_funding_time,
+        bounty: funding_bounty(&config, &state)
     })
 }
 
@@ -234,11 +345,12 @@ fn query_position(deps: Deps, amm_addr: Addr, user_addr: Addr) -> StdResult<Posi
             current_value: position.openingValue,
             margin_ratio: Decimal256::from_uint256(1u128),
             pnl: 0i64,
-            funding: FundingResponse { amount: Uint256::zero(), sign: Sign::Positive }
+            funding: FundingResponse { amount: SignedDecimal256::zero() },
+            funding_paid: position.funding_paid
         });
     }
 
-    let (pnl, funding_response, new_position_value, margin_adjusted) = simulate_close(deps, amm_addr, position.clone())?;
+    let (pnl, funding_response, new_position_value, margin_adjusted, _) = simulate_close(deps, amm_addr, position.clone())?;
 
     let margin_ratio: Decimal256 = Decimal256::from_ratio(margin_adjusted, position.openingValue);
 
@@ -251,6 +363,7 @@ fn query_position(deps: Deps, amm_addr: Addr, user_addr: Addr) -> StdResult<Posi
         current_value: new_position_value,
         margin_ratio: margin_ratio,
         pnl: pnl,
+        funding_paid: position.funding_paid - funding_response.amount,
         funding: funding_response
     })
 }
@@ -19,7 +19,22 @@ pub enum ContractError {
     #[error("Position still open")]
     PositionNotOpen {},
 
+    #[error("Margin does not cover the trading fee")]
+    InsufficientMargin {},
+
     #[error("Position margin ratio is outiside lqiuidatio range")]
     Unliquidatable {},
 
+    #[error("Not time to call for funding yet")]
+    NotTime {},
+
+    #[error("Market has not been shut down")]
+    MarketNotSettled {},
+
+    #[error("Oracle price is older than the allowed age")]
+    StaleOracle {},
+
+    #[error("Oracle price moved more than the allowed deviation")]
+    OracleDeviation {},
+
 }
@@ -3,12 +3,13 @@ use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
 use terraswap::asset::{Asset, AssetInfo};
 use terraswap::querier::{query_supply,query_balance};
 use cw20::{Cw20ExecuteMsg};
-use seesaw::vamm::{ExecuteMsg as VammExecuteMsg, QueryMsg as VammQueryMsg };
+use seesaw::vamm::{AmountKind, ExecuteMsg as VammExecuteMsg, OracleStatus, OracleStatusResponse, QueryMsg as VammQueryMsg, QuoteResponse, StateResponse as VammStateResponse };
 
 use crate::error::ContractError;
-use crate::state::{ CONFIG, Config, STATE, State, POSITIONS, Position, MARKETS, Market };
+use crate::state::{ CONFIG, Config, STATE, State, POSITIONS, Position, MARKETS };
 
-use seesaw::bank::{Direction, FundingResponse, Sign};
+use seesaw::bank::{Direction, FundingResponse};
+use seesaw::number::SignedDecimal256;
 
 // Add Margin to a vAMM of selection
 pub fn add_margin(
@@ -47,7 +48,8 @@ pub fn add_margin(
                 openingValue: Uint256::zero(),
                 direction: Direction::NOT_SET,
                 margin: deposit_amount,
-                last_cumulative_funding: Decimal256::zero()
+                last_cumulative_funding: SignedDecimal256::zero(),
+                funding_paid: SignedDecimal256::zero()
             };
             POSITIONS.save(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()), &new_position);
         }
@@ -76,11 +78,14 @@ pub fn open_position(
     info: MessageInfo,
     market_addr: Addr,
     direction: Direction,
-    open_value: Uint256 // Value of position that would like to open at, eg. 10,000 UST
+    open_value: Uint256, // Value of position that would like to open at, eg. 10,000 UST
+    base_amount_limit: Option<Uint256>,
+    belief_price: Option<Decimal256>,
+    max_spread: Option<Decimal256>
 ) -> Result<Response, ContractError> {
 
     // Crash if market doesn't exist
-    let market = MARKETS.load(deps.storage, market_addr.as_bytes())?;
+    MARKETS.load(deps.storage, market_addr.as_bytes())?;
 
     let position = POSITIONS.load(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()))?;
 
@@ -94,7 +99,10 @@ pub fn open_position(
         contract_addr: market_addr.to_string(),
         msg: to_binary(&VammExecuteMsg::SwapIn {
             direction: direction.clone(),
-            quote_asset_amount: open_value
+            quote_asset_amount: open_value,
+            base_amount_limit,
+            belief_price,
+            max_spread
         })?,
         funds: vec![],
     });
@@ -106,46 +114,59 @@ pub fn open_position(
 
     let config: Config = CONFIG.load(deps.storage)?;
 
-    let position_size: Uint256 = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+    let simulation: QuoteResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
         contract_addr: market_addr.to_string(),
-        msg: to_binary(&VammQueryMsg::SimulateIn { quoteAmount: open_value, direction: direction.clone(), })?,
+        msg: to_binary(&VammQueryMsg::Quote { side: direction.clone(), amount: open_value, amount_kind: AmountKind::QUOTE })?,
     }))?;
-    
-    // 3. Update position to reflect opened position
+
+    // 3. Deduct trading fee from margin and send it to the fee recipient
+    let fee = simulation.toll_fee + simulation.spread_fee;
+    if fee > position.margin {
+        return Err(ContractError::InsufficientMargin {});
+    }
+
+    if fee > Uint256::zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: deps.api.addr_humanize(&config.fee_recipient)?.to_string(),
+            amount: vec![Coin {
+                denom: config.stable_denom.clone(),
+                amount: Uint128::from(fee),
+            }],
+        }));
+    }
+
+    // 4. Update position to reflect opened position
     let mut new_position = position.clone();
+    new_position.margin = position.margin - fee;
     new_position.openingValue = open_value;
-    new_position.positionSize = position_size;
+    new_position.positionSize = simulation.base_amount;
     new_position.direction = direction;
-    new_position.last_cumulative_funding = market.cumulative_funding_premium;
+    new_position.last_cumulative_funding = query_cumulative_funding(deps.as_ref(), &market_addr)?;
 
     POSITIONS.save(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()), &new_position)?;
 
-    // 4. Send swap messages
+    // 5. Send swap messages
 
     Ok(Response::new()
         .add_messages(messages)
         .add_attributes(vec![
             ("action", "open position"),
             ("openingValue", new_position.openingValue.to_string().as_str()),
-            ("positionSize", new_position.positionSize.to_string().as_str())
+            ("positionSize", new_position.positionSize.to_string().as_str()),
+            ("fee", fee.to_string().as_str())
         ])
     )
 }
 
-fn safe_subtract_min_zero(left: Uint256, right: Uint256) -> Uint256{
-    if left > right {
-        return left - right
-    } else {
-        return Uint256::zero()
-    }
-}
-
 // Add Margin to a vAMM of selection
 pub fn close_position(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    market_addr: Addr
+    market_addr: Addr,
+    quote_amount_limit: Option<Uint256>,
+    belief_price: Option<Decimal256>,
+    max_spread: Option<Decimal256>
 ) -> Result<Response, ContractError> {
 
     // Crash if market doesn't exist
@@ -161,14 +182,20 @@ pub fn close_position(
 
     let config: Config = CONFIG.load(deps.storage)?;
 
-    let (_,_,_,margin_adjusted) = simulate_close(deps.as_ref(), market_addr.clone(), position.clone())?;
+    let (_,funding,_,margin_adjusted,fee) = simulate_close(deps.as_ref(), market_addr.clone(), position.clone())?;
+
+    // 2. Trading fee is taken out of what is left of the margin
+    let fee_paid = if fee > margin_adjusted { margin_adjusted } else { fee };
 
     /// 4. Perform Swap on vAMM
     let msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: market_addr.to_string(),
         msg: to_binary(&VammExecuteMsg::SwapOut {
             direction: position.direction.clone(),
-            base_asset_amount: position.positionSize
+            base_asset_amount: position.positionSize,
+            quote_amount_limit,
+            belief_price,
+            max_spread
         })?,
         funds: vec![],
     });
@@ -180,12 +207,22 @@ pub fn close_position(
     let msg: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
         to_address: info.sender.to_string(),
         amount: vec![Coin {
-            denom: config.stable_denom,
-            amount: Uint128::from(margin_adjusted),
+            denom: config.stable_denom.clone(),
+            amount: Uint128::from(margin_adjusted - fee_paid),
         }],
     });
 
     messages.push(msg);
+
+    if fee_paid > Uint256::zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: deps.api.addr_humanize(&config.fee_recipient)?.to_string(),
+            amount: vec![Coin {
+                denom: config.stable_denom.clone(),
+                amount: Uint128::from(fee_paid),
+            }],
+        }));
+    }
     
     // 5. Clear the position
     let mut new_position = Position {
@@ -193,7 +230,8 @@ pub fn close_position(
         openingValue: Uint256::zero(),
         positionSize: Uint256::zero(),
         direction: Direction::NOT_SET,
-        last_cumulative_funding: Decimal256::zero()
+        last_cumulative_funding: SignedDecimal256::zero(),
+        funding_paid: position.funding_paid - funding.amount
     };
 
     POSITIONS.save(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()), &new_position)?;
@@ -204,7 +242,76 @@ pub fn close_position(
         .add_attributes(vec![
             ("action", "close position"),
             ("openingValue", new_position.openingValue.to_string().as_str()),
-            ("positionSize", new_position.positionSize.to_string().as_str())
+            ("positionSize", new_position.positionSize.to_string().as_str()),
+            ("fee", fee_paid.to_string().as_str())
+        ])
+    )
+}
+
+// Close a position of a shut down market at the settlement price, without trading on the vAMM
+pub fn settle_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_addr: Addr
+) -> Result<Response, ContractError> {
+
+    // Crash if market doesn't exist
+    MARKETS.load(deps.storage, market_addr.as_bytes())?;
+
+    let position: Position = POSITIONS.load(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()))?;
+
+    if position.direction == Direction::NOT_SET {
+        return Err(ContractError::PositionNotOpen {});
+    }
+
+    let state: VammStateResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: market_addr.to_string(),
+        msg: to_binary(&VammQueryMsg::State { })?,
+    }))?;
+
+    let settlement_price = match state.settlement_price {
+        Some(price) => price,
+        None => return Err(ContractError::MarketNotSettled {}),
+    };
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    // 1. Value the position at the settlement price, no slippage and no trading fee
+    let settlement_value = position.positionSize * settlement_price;
+    let (_, funding, margin_adjusted) = realize_position(deps.as_ref(), &market_addr, &position, settlement_value)?;
+
+    // 2. Transfer back margin to user wallet
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if margin_adjusted > Uint256::zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: config.stable_denom.clone(),
+                amount: Uint128::from(margin_adjusted),
+            }],
+        }));
+    }
+
+    // 3. Clear the position
+    let new_position = Position {
+        margin: Uint256::zero(),
+        openingValue: Uint256::zero(),
+        positionSize: Uint256::zero(),
+        direction: Direction::NOT_SET,
+        last_cumulative_funding: SignedDecimal256::zero(),
+        funding_paid: position.funding_paid - funding.amount
+    };
+
+    POSITIONS.save(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()), &new_position)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", "settle position"),
+            ("settlement_price", settlement_price.to_string().as_str()),
+            ("settlement_value", settlement_value.to_string().as_str()),
+            ("margin", margin_adjusted.to_string().as_str())
         ])
     )
 }
@@ -214,77 +321,71 @@ pub fn simulate_close(
     deps: Deps,
     market_addr: Addr,
     position: Position
-    // Returns PNL, New Position Size, MarginLeft
-) -> StdResult<(i64, FundingResponse, Uint256, Uint256)> {
+    // Returns PNL, New Position Size, MarginLeft, Closing Fee
+) -> StdResult<(i64, FundingResponse, Uint256, Uint256, Uint256)> {
 
     let config: Config = CONFIG.load(deps.storage)?;
 
-    // Get current position value
-    let new_position_value: Uint256 = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+    // Get current position value, closing trades base the other way
+    let closing_side = match position.direction {
+        Direction::LONG => Direction::SHORT,
+        _ => Direction::LONG,
+    };
+    let simulation: QuoteResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
         contract_addr: market_addr.to_string(),
-        msg: to_binary(&VammQueryMsg::SimulateOut { baseAmount: position.positionSize.clone(), direction: position.direction.clone() })?,
+        msg: to_binary(&VammQueryMsg::Quote { side: closing_side, amount: position.positionSize, amount_kind: AmountKind::BASE })?,
     }))?;
+    let new_position_value: Uint256 = simulation.quote_amount;
+
+    let (pnl, funding_response, margin_funding_pnl_adjusted) = realize_position(deps, &market_addr, &position, new_position_value)?;
+
+    Ok((pnl,funding_response,new_position_value,margin_funding_pnl_adjusted,simulation.toll_fee + simulation.spread_fee))
+
+}
+
+// PNL, funding and margin left of a position worth new_position_value
+fn realize_position(
+    deps: Deps,
+    market_addr: &Addr,
+    position: &Position,
+    new_position_value: Uint256
+) -> StdResult<(i64, FundingResponse, Uint256)> {
 
    // 3. Calculate funding fee realized
     // TO DO: find a way to implement funding fee
-    let market: Market = MARKETS.load(deps.storage, &market_addr.as_bytes())?;
+    // Crash if market doesn't exist
+    MARKETS.load(deps.storage, market_addr.as_bytes())?;
 
-    let funding: Decimal256 = if market.cumulative_funding_premium > position.last_cumulative_funding {
-        (market.cumulative_funding_premium - position.last_cumulative_funding) * Decimal256::from_uint256(position.positionSize)
-    } else {
-        (position.last_cumulative_funding - market.cumulative_funding_premium) * Decimal256::from_uint256(position.positionSize)
-    };
+    // Funding accrues every second on the vAMM, so read the live value rather than the cached one
+    let cumulative_funding_premium = query_cumulative_funding(deps, market_addr)?;
 
-    let mut funding_response: FundingResponse;
+    // Longs pay when the cumulative premium increases, shorts get paid
+    let funding_delta = (cumulative_funding_premium - position.last_cumulative_funding) * position.positionSize;
 
     // 2. Calculate margin with pnl and funding realized
-    let margin_funding_pnl_adjusted: Uint256 = match position.direction {
+    let (value_change, funding) = match position.direction {
         Direction::LONG => {
-            // margin_pnl_adjusted = old_margin + (curr_value - open_value) = old_margin - open_value + curr_value
-            let intermediary1 = position.margin + new_position_value;
-
-            let intermediary2 = if market.cumulative_funding_premium > position.last_cumulative_funding {
-                funding_response = FundingResponse {
-                    amount: funding * Uint256::one(),
-                    sign: Sign::Negative
-                };
-                safe_subtract_min_zero(intermediary1, funding * Uint256::one()) // If funding premium increased, pays
-            } else {
-                funding_response = FundingResponse {
-                    amount: funding * Uint256::one(),
-                    sign: Sign::Positive
-                };
-                intermediary1 + funding * Uint256::one() // If funding premium decreased, gets paid
-            };
-            
-            safe_subtract_min_zero(intermediary2, position.openingValue)
-
+            (SignedDecimal256::from_uint256(new_position_value) - SignedDecimal256::from_uint256(position.openingValue), -funding_delta)
         },
         Direction::SHORT => {
-
-            let intermediary1 = position.margin + position.openingValue;
-
-            let intermediary2 = if market.cumulative_funding_premium > position.last_cumulative_funding {
-                funding_response = FundingResponse {
-                    amount: funding * Uint256::one(),
-                    sign: Sign::Positive
-                };
-                intermediary1 + funding * Uint256::one() // If funding premium increased, gets paid
-            } else {
-                funding_response = FundingResponse {
-                    amount: funding * Uint256::one(),
-                    sign: Sign::Negative
-                };
-                safe_subtract_min_zero(intermediary1, funding * Uint256::one()) // If funding premium decreased, pays
-            };
-            
-            safe_subtract_min_zero(intermediary2, new_position_value)
+            (SignedDecimal256::from_uint256(position.openingValue) - SignedDecimal256::from_uint256(new_position_value), funding_delta)
         },
         Direction::NOT_SET => {
             return Err(StdError::GenericErr { msg: "UNSET DIRECTION".to_string() });
         },
     };
 
+    let margin_funding_pnl_adjusted = SignedDecimal256::from_uint256(position.margin) + value_change + funding;
+
+    // Losses beyond the margin are bad debt, margin left bottoms out at zero
+    let margin_funding_pnl_adjusted: Uint256 = if margin_funding_pnl_adjusted.is_negative() {
+        Uint256::zero()
+    } else {
+        margin_funding_pnl_adjusted.abs() * Uint256::one()
+    };
+
+    let funding_response = FundingResponse { amount: funding };
+
     // Convert all to i64
     let signed_curr_value: i64 = u128::from(new_position_value) as u64 as i64;
     let signed_open_value: i64 = u128::from(position.openingValue) as u64 as i64;
@@ -302,11 +403,41 @@ pub fn simulate_close(
         },
     };
 
-    Ok((pnl,funding_response,new_position_value,margin_funding_pnl_adjusted))
+    Ok((pnl,funding_response,margin_funding_pnl_adjusted))
 
 }
 
 
+// Cumulative funding premium of a market, accrued up to the current block
+fn query_cumulative_funding(
+    deps: Deps,
+    market_addr: &Addr
+) -> StdResult<SignedDecimal256> {
+    let state: VammStateResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: market_addr.to_string(),
+        msg: to_binary(&VammQueryMsg::State { })?,
+    }))?;
+
+    Ok(state.funding_premium_cumulative)
+}
+
+// Refuse to act on a market whose oracle price fails its staleness or deviation guards
+fn assert_oracle_valid(
+    deps: Deps,
+    market_addr: &Addr
+) -> Result<(), ContractError> {
+    let oracle_status: OracleStatusResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: market_addr.to_string(),
+        msg: to_binary(&VammQueryMsg::OracleStatus { })?,
+    }))?;
+
+    match oracle_status.status {
+        OracleStatus::Valid => Ok(()),
+        OracleStatus::Stale => Err(ContractError::StaleOracle {}),
+        OracleStatus::Deviation => Err(ContractError::OracleDeviation {}),
+    }
+}
+
 // Add Margin to a vAMM of selection
 pub fn liquidate(
     deps: DepsMut,
@@ -325,11 +456,14 @@ pub fn liquidate(
         return Err(ContractError::PositionNotOpen {});
     }
 
+    // Do not liquidate against a bad index price
+    assert_oracle_valid(deps.as_ref(), &market_addr)?;
+
     // 1. Simulate Swap on AMM
 
     let config: Config = CONFIG.load(deps.storage)?;
 
-    let (_,_,_,margin_adjusted) = simulate_close(deps.as_ref(), market_addr.clone(), position.clone())?; //  Get current margin
+    let (_,funding,_,margin_adjusted,_) = simulate_close(deps.as_ref(), market_addr.clone(), position.clone())?; //  Get current margin
 
     // 2. Check ratio
 
@@ -345,7 +479,10 @@ pub fn liquidate(
         contract_addr: market_addr.to_string(),
         msg: to_binary(&VammExecuteMsg::SwapOut {
             direction: position.direction.clone(),
-            base_asset_amount: position.positionSize
+            base_asset_amount: position.positionSize,
+            quote_amount_limit: None,
+            belief_price: None,
+            max_spread: None
         })?,
         funds: vec![],
     });
@@ -392,10 +529,11 @@ pub fn liquidate(
         openingValue: Uint256::zero(),
         positionSize: Uint256::zero(),
         direction: Direction::NOT_SET,
-        last_cumulative_funding: Decimal256::zero()
+        last_cumulative_funding: SignedDecimal256::zero(),
+        funding_paid: position.funding_paid - funding.amount
     };
 
-    POSITIONS.save(deps.storage, (market_addr.as_bytes(), info.sender.as_bytes()), &new_position)?;
+    POSITIONS.save(deps.storage, (market_addr.as_bytes(), holder_addr.as_bytes()), &new_position)?;
 
     // 8. Send messages
     Ok(Response::new()
@@ -6,6 +6,7 @@ use cosmwasm_std::{CanonicalAddr, Api, Storage, StdResult, Order};
 use cw_storage_plus::{Item,Map};
 
 use seesaw::bank::{Direction};
+use seesaw::number::SignedDecimal256;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -13,12 +14,15 @@ pub struct Config {
     pub owner_addr: CanonicalAddr,
     pub stable_denom: String,
     pub liquidation_ratio: Decimal256,
-    pub liquidation_reward: Decimal256
+    pub liquidation_reward: Decimal256,
+    pub fee_recipient: CanonicalAddr,
+    pub funding_bounty: Uint256 // In stable denom, per funding settlement
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub last_cumulative_funding_fee: u64,
+    pub fee_pool: Uint256, // Stable deposited through FundFeePool for keeper bounties, separate from trading fees
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -27,13 +31,15 @@ pub struct Position {
     pub direction: Direction, // true = longing, false = shorting
     pub openingValue: Uint256, // Amount of base asset (i.e. UST) that is used in shorting/longing, at the time of opening
     pub positionSize: Uint256, // Amount of quoted assets that is being longed/shorted
-    pub last_cumulative_funding: Decimal256
+    pub last_cumulative_funding: SignedDecimal256,
+    pub funding_paid: SignedDecimal256 // Funding paid by closed positions, negative if received
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Market {
     pub contract_addr: CanonicalAddr,
-    pub cumulative_funding_premium: Decimal256,
+    pub cumulative_funding_premium: SignedDecimal256,
+    pub last_funding_time: u64, // Settlement the last bounty was paid for, as next_funding_time of the vAMM in seconds
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
@@ -8,7 +8,8 @@ use std::collections::HashMap;
 
 use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
-use seesaw::vamm::{Funding, QueryMsg as VammQueryMsg, StateResponse as VammStateResponse, WhoPays};
+use seesaw::vamm::{AmountKind, Funding, OracleStatus, OracleStatusResponse, QueryMsg as VammQueryMsg, QuoteResponse, StateResponse as VammStateResponse, WhoPays};
+use seesaw::number::SignedDecimal256;
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
 /// this uses our CustomQuerier.
@@ -29,6 +30,11 @@ pub struct WasmMockQuerier {
     base: MockQuerier<TerraQueryWrapper>,
     token_querier: TokenQuerier,
     tax_querier: TaxQuerier,
+    oracle_status: OracleStatus,
+    fee_ratios: (Decimal256, Decimal256),
+    funding_premium: SignedDecimal256,
+    settlement_price: Option<Decimal256>,
+    next_funding_time: u64,
 }
 
 #[derive(Clone, Default)]
@@ -135,27 +141,51 @@ impl WasmMockQuerier {
                             to_binary(&VammStateResponse {
                                 base_asset_reserve: Uint256::from(1000u128),
                                 quote_asset_reserve: Uint256::from(1_000_000u128),
-                                funding_premium_cumulative: Decimal256::from_uint256(10_000u128),
+                                funding_premium_cumulative: self.funding_premium,
                                 funding_fee: Funding {
                                     amount: Decimal256::from_ratio(1, 1000),
                                     who_pays: WhoPays::LONG
                                 },
                                 market_price: Decimal256::from_uint256(1100u128),
-                                underlying_price: Decimal256::from_uint256(1000u128)
+                                underlying_price: Decimal256::from_uint256(1000u128),
+                                total_long_size: Uint256::zero(),
+                                total_short_size: Uint256::zero(),
+                                net_position_size: 0i64,
+                                settlement_price: self.settlement_price,
+                                next_funding_time: self.next_funding_time
                             })
                             .unwrap(),
                         ))
                     },
-                    VammQueryMsg::SimulateIn { quoteAmount, direction} => {
+                    VammQueryMsg::Quote { side: _, amount, amount_kind } => {
+                        // Opening buys base at 10, closing sells it at 9
+                        let (base_amount, quote_amount) = match amount_kind {
+                            AmountKind::QUOTE => (amount / Decimal256::from_uint256(10u128), amount),
+                            AmountKind::BASE => (amount, amount * Uint256::from(9u128)),
+                        };
                         SystemResult::Ok(ContractResult::Ok(
-                            to_binary(&(quoteAmount / Decimal256::from_uint256(10u128))
-                        ).unwrap(),
+                            to_binary(&QuoteResponse {
+                                base_amount,
+                                quote_amount,
+                                average_price: Decimal256::from_ratio(quote_amount, base_amount),
+                                mark_price_before: Decimal256::from_uint256(1100u128),
+                                mark_price_after: Decimal256::from_uint256(1100u128),
+                                price_impact: Decimal256::zero(),
+                                toll_fee: quote_amount * self.fee_ratios.0,
+                                spread_fee: quote_amount * self.fee_ratios.1,
+                                funding_rate: Funding { amount: Decimal256::zero(), who_pays: WhoPays::LONG }
+                            })
+                            .unwrap(),
                         ))
                     },
-                    VammQueryMsg::SimulateOut { baseAmount, direction} => {
+                    VammQueryMsg::OracleStatus {} => {
                         SystemResult::Ok(ContractResult::Ok(
-                            to_binary(&(baseAmount * Uint256::from(9u128))
-                        ).unwrap(),
+                            to_binary(&OracleStatusResponse {
+                                status: self.oracle_status.clone(),
+                                price: Decimal256::from_uint256(1000u128),
+                                last_updated: 1_571_797_419u64
+                            })
+                            .unwrap(),
                         ))
                     }
 
@@ -235,6 +265,11 @@ impl WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
             tax_querier: TaxQuerier::default(),
+            oracle_status: OracleStatus::Valid,
+            fee_ratios: (Decimal256::zero(), Decimal256::zero()),
+            funding_premium: SignedDecimal256::from_uint256(10_000u128.into()),
+            settlement_price: None,
+            next_funding_time: 0,
         }
     }
 
@@ -248,6 +283,31 @@ impl WasmMockQuerier {
         self.tax_querier = TaxQuerier::new(rate, caps);
     }
 
+    // configure the oracle guard status reported by the vAMM
+    pub fn with_oracle_status(&mut self, status: OracleStatus) {
+        self.oracle_status = status;
+    }
+
+    // configure the toll and spread ratios charged by the vAMM
+    pub fn with_fee_ratios(&mut self, toll_ratio: Decimal256, spread_ratio: Decimal256) {
+        self.fee_ratios = (toll_ratio, spread_ratio);
+    }
+
+    // configure the cumulative funding premium reported by the vAMM
+    pub fn with_funding_premium(&mut self, funding_premium: SignedDecimal256) {
+        self.funding_premium = funding_premium;
+    }
+
+    // configure when the vAMM accepts the next funding settlement, in seconds
+    pub fn with_next_funding_time(&mut self, next_funding_time: u64) {
+        self.next_funding_time = next_funding_time;
+    }
+
+    // configure the settlement price reported by the vAMM, None while the market is open
+    pub fn with_settlement_price(&mut self, settlement_price: Option<Decimal256>) {
+        self.settlement_price = settlement_price;
+    }
+
     pub fn with_balance(&mut self, balances: &[(&String, Vec<Coin>)]) {
         for (addr, balance) in balances {
             self.base.update_balance(addr.to_string(), balance.clone());
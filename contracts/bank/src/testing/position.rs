@@ -3,10 +3,14 @@ use std::str::FromStr;
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{Addr, BankMsg, Coin, ContractResult, CosmosMsg, Decimal, Reply, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg, from_binary, to_binary};
-use seesaw::bank::{Direction, ExecuteMsg, InstantiateMsg, PositionResponse, QueryMsg};
+use seesaw::bank::{Direction, ExecuteMsg, FundingResponse, FundingScheduleResponse, InstantiateMsg, PositionResponse, QueryMsg};
+use seesaw::number::SignedDecimal256;
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
+use seesaw::vamm::{ExecuteMsg as VammExecuteMsg, OracleStatus};
+
 use crate::contract::{instantiate, execute, query};
+use crate::error::ContractError;
 use crate::testing::mock_querier::mock_dependencies;
 
 #[test]
@@ -19,6 +23,8 @@ fn add_margin() {
         stable_denom: "uusd".to_string(),
         liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
         liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
     };
 
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -68,6 +74,8 @@ fn open_position() {
         stable_denom: "uusd".to_string(),
         liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
         liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
     };
 
 
@@ -100,7 +108,7 @@ fn open_position() {
 
     // Open Position
 
-    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG };
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
 
     let info = mock_info("depositor", &vec![]);
 
@@ -125,6 +133,8 @@ fn close_position() {
         stable_denom: "uusd".to_string(),
         liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
         liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
     };
 
 
@@ -157,7 +167,7 @@ fn close_position() {
 
     // 3. Open Position
 
-    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG };
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
 
     let info = mock_info("depositor", &vec![]);
 
@@ -174,7 +184,7 @@ fn close_position() {
 
     // 4. Close Position
 
-    let msg = ExecuteMsg::ClosePosition { market_addr: "bank0000".to_string() };
+    let msg = ExecuteMsg::ClosePosition { market_addr: "bank0000".to_string(), quote_amount_limit: None, belief_price: None, max_spread: None };
 
     let info = mock_info("depositor", &vec![]);
 
@@ -193,6 +203,501 @@ fn close_position() {
 }
 
 
+#[test]
+fn slippage_limits_forwarded() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() }).unwrap();
+
+    let info = mock_info("depositor", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100u128)
+    }]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() }).unwrap();
+
+    let info = mock_info("depositor", &vec![]);
+
+    let msg = ExecuteMsg::OpenPosition {
+        market_addr: "bank0000".to_string(),
+        open_value: Uint256::from(500u128),
+        direction: Direction::LONG,
+        base_amount_limit: Some(Uint256::from(49u128)),
+        belief_price: Some(Decimal256::from_uint256(10u128)),
+        max_spread: Some(Decimal256::percent(1))
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    assert_eq!(res.messages[0], SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "bank0000".to_string(),
+        msg: to_binary(&VammExecuteMsg::SwapIn {
+            direction: Direction::LONG,
+            quote_asset_amount: Uint256::from(500u128),
+            base_amount_limit: Some(Uint256::from(49u128)),
+            belief_price: Some(Decimal256::from_uint256(10u128)),
+            max_spread: Some(Decimal256::percent(1))
+        }).unwrap(),
+        funds: vec![],
+    })));
+
+    let msg = ExecuteMsg::ClosePosition {
+        market_addr: "bank0000".to_string(),
+        quote_amount_limit: Some(Uint256::from(440u128)),
+        belief_price: None,
+        max_spread: Some(Decimal256::percent(2))
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    assert_eq!(res.messages[0], SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "bank0000".to_string(),
+        msg: to_binary(&VammExecuteMsg::SwapOut {
+            direction: Direction::LONG,
+            base_asset_amount: Uint256::from(50u128),
+            quote_amount_limit: Some(Uint256::from(440u128)),
+            belief_price: None,
+            max_spread: Some(Decimal256::percent(2))
+        }).unwrap(),
+        funds: vec![],
+    })));
+}
+
+#[test]
+fn trading_fees() {
+    let mut deps = mock_dependencies(&[]);
+
+    // 1% toll and 1% spread on notional
+    deps.querier.with_fee_ratios(Decimal256::percent(1), Decimal256::percent(1));
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() };
+
+    // 1. Register Market
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() };
+
+    let info = mock_info("depositor", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100u128)
+    }]);
+
+    // 2. Add Margin
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 3. Open Position, paying 2% of 500 in fees
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
+
+    let info = mock_info("depositor", &vec![]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    assert_eq!(res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "collector0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(10u128)
+            }]
+        }))
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "depositor".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position.margin, Uint256::from(90u128));
+
+    // 4. Close Position, paying 1% toll and 1% spread on 450, each rounded down, out of the 40 margin left
+    let msg = ExecuteMsg::ClosePosition { market_addr: "bank0000".to_string(), quote_amount_limit: None, belief_price: None, max_spread: None };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    assert_eq!(res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "depositor".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(32u128)
+            }]
+        }))
+    );
+    assert_eq!(res.messages[2],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "collector0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(8u128)
+            }]
+        }))
+    );
+}
+
+#[test]
+fn funding_payments() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() }).unwrap();
+
+    let info = mock_info("depositor", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100u128)
+    }]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() }).unwrap();
+
+    // Long 50 base for 500, worth 450 on close
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
+    let info = mock_info("depositor", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Premium increases by 0.2, longs pay 10
+    deps.querier.with_funding_premium(SignedDecimal256::from_str("10000.2").unwrap());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "depositor".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position.funding, FundingResponse { amount: SignedDecimal256::from_str("-10").unwrap() });
+    assert_eq!(position.margin_left, Uint256::from(40u128));
+    assert_eq!(position.funding_paid, SignedDecimal256::from_str("10").unwrap());
+
+    // Premium decreases by 0.2, longs get paid 10
+    deps.querier.with_funding_premium(SignedDecimal256::from_str("9999.8").unwrap());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "depositor".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position.funding, FundingResponse { amount: SignedDecimal256::from_str("10").unwrap() });
+    assert_eq!(position.margin_left, Uint256::from(60u128));
+    assert_eq!(position.funding_paid, SignedDecimal256::from_str("-10").unwrap());
+
+    // Funding received stays on record after closing
+    let msg = ExecuteMsg::ClosePosition { market_addr: "bank0000".to_string(), quote_amount_limit: None, belief_price: None, max_spread: None };
+    let info = mock_info("depositor", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "depositor".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position.direction, Direction::NOT_SET);
+    assert_eq!(position.funding_paid, SignedDecimal256::from_str("-10").unwrap());
+}
+
+#[test]
+fn settle_position() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() }).unwrap();
+
+    let info = mock_info("depositor", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100u128)
+    }]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() }).unwrap();
+
+    // Long 50 base for 500
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
+    let info = mock_info("depositor", &vec![]);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::SettlePosition { market_addr: "bank0000".to_string() };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err() {
+        ContractError::MarketNotSettled {} => {}
+        _ => panic!("Must return market not settled error"),
+    }
+
+    // Settles at 11 per base, worth 550
+    deps.querier.with_settlement_price(Some(Decimal256::from_uint256(11u128)));
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+        to_address: "depositor".to_string(),
+        amount: vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(150u128),
+        }],
+    }))]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "depositor".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position.direction, Direction::NOT_SET);
+    assert_eq!(position.margin, Uint256::zero());
+}
+
+#[test]
+fn funding_bounty() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::from(10u128),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() }).unwrap();
+
+    // Unregistered markets pay nothing
+    let msg = ExecuteMsg::UpdateFunding { market_addr: "market0000".to_string() };
+    let info = mock_info("keeper", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+
+    // Too early
+    let env = mock_env();
+    deps.querier.with_next_funding_time(env.block.time.seconds() + 1);
+
+    let msg = ExecuteMsg::UpdateFunding { market_addr: "bank0000".to_string() };
+    let info = mock_info("keeper", &vec![]);
+    match execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err() {
+        ContractError::NotTime {} => {}
+        _ => panic!("Must return not time error"),
+    }
+
+    deps.querier.with_next_funding_time(env.block.time.seconds());
+
+    // Empty pool, settlement goes through without a bounty
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    // Settlement already done in this block
+    match execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err() {
+        ContractError::NotTime {} => {}
+        _ => panic!("Must return not time error"),
+    }
+
+    let mut env = env;
+    env.block.time = env.block.time.plus_seconds(28_800);
+    deps.querier.with_next_funding_time(env.block.time.seconds());
+
+    let fund = mock_info("funder", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(15u128)
+    }]);
+    execute(deps.as_mut(), env.clone(), fund, ExecuteMsg::FundFeePool {}).unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::FundingSchedule { market_addr: "bank0000".to_string() }).unwrap();
+    let schedule: FundingScheduleResponse = from_binary(&res).unwrap();
+    assert_eq!(schedule, FundingScheduleResponse {
+        next_funding_time: env.block.time.seconds(),
+        bounty: Uint256::from(10u128)
+    });
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages[2], SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+        to_address: "keeper".to_string(),
+        amount: vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(10u128),
+        }],
+    })));
+
+    // Only what is left in the pool is paid
+    env.block.time = env.block.time.plus_seconds(28_800);
+    deps.querier.with_next_funding_time(env.block.time.seconds());
+
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(res.messages[2], SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+        to_address: "keeper".to_string(),
+        amount: vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(5u128),
+        }],
+    })));
+
+    let res = query(deps.as_ref(), env, QueryMsg::FundingSchedule { market_addr: "bank0000".to_string() }).unwrap();
+    let schedule: FundingScheduleResponse = from_binary(&res).unwrap();
+    assert_eq!(schedule.bounty, Uint256::zero());
+}
+
+#[test]
+fn liquidate_oracle_guards() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() };
+
+    // 1. Register Market
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() };
+
+    let info = mock_info("depositor", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100u128)
+    }]);
+
+    // 2. Add Margin
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 3. Open Position
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
+
+    let info = mock_info("depositor", &vec![]);
+
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 4. Liquidate with a stale oracle
+    deps.querier.with_oracle_status(OracleStatus::Stale);
+
+    let msg = ExecuteMsg::Liquidate { market_addr: "bank0000".to_string(), holder_addr: "depositor".to_string() };
+
+    let info = mock_info("liquidator", &vec![]);
+
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    match err {
+        ContractError::StaleOracle {} => {}
+        _ => panic!("Must return stale oracle error"),
+    }
+
+    // 5. Liquidate with a price that jumped
+    deps.querier.with_oracle_status(OracleStatus::Deviation);
+
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    match err {
+        ContractError::OracleDeviation {} => {}
+        _ => panic!("Must return oracle deviation error"),
+    }
+
+    // 6. With a valid oracle, the margin ratio check applies
+    deps.querier.with_oracle_status(OracleStatus::Valid);
+
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    match err {
+        ContractError::Unliquidatable {} => {}
+        _ => panic!("Must return unliquidatable error"),
+    }
+}
+
+#[test]
+fn liquidate_clears_holder_position() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        liquidation_ratio: Decimal256::from_str("0.0625").unwrap(),
+        liquidation_reward: Decimal256::from_str("0.05").unwrap(),
+        fee_recipient: "collector0000".to_string(),
+        funding_bounty: Uint256::zero(),
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("owner", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RegisterMarket { contract_addr: "bank0000".to_string() }).unwrap();
+
+    // Holder opens a long of 50 base with 100 margin
+    let msg = ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() };
+    let info = mock_info("depositor", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100u128)
+    }]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::OpenPosition { market_addr: "bank0000".to_string(), open_value: Uint256::from(500u128), direction: Direction::LONG, base_amount_limit: None, belief_price: None, max_spread: None };
+    let info = mock_info("depositor", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Liquidator has margin of its own in the market
+    let msg = ExecuteMsg::DepositStable { market_addr: "bank0000".to_string() };
+    let info = mock_info("liquidator", &vec![Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(50u128)
+    }]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "liquidator".to_string() }).unwrap();
+    let liquidator_position: PositionResponse = from_binary(&res).unwrap();
+
+    // Closing loses 50 and funding takes 20, leaving 30 of margin on 500 of opening value
+    deps.querier.with_funding_premium(SignedDecimal256::new(Decimal256::from_str("10000.4").unwrap(), false));
+
+    let msg = ExecuteMsg::Liquidate { market_addr: "bank0000".to_string(), holder_addr: "depositor".to_string() };
+    let info = mock_info("liquidator", &vec![]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "depositor".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position.direction, Direction::NOT_SET);
+    assert_eq!(position.margin, Uint256::zero());
+    assert_eq!(position.positionSize, Uint256::zero());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Position { market_addr: "bank0000".to_string(), user_addr: "liquidator".to_string() }).unwrap();
+    let position: PositionResponse = from_binary(&res).unwrap();
+
+    assert_eq!(position, liquidator_position);
+}
+
 #[test]
 fn test_types() {
 
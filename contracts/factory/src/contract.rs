@@ -0,0 +1,212 @@
+use cosmwasm_std::{Binary, CanonicalAddr, ContractResult, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, StdError, StdResult, SubMsg, WasmMsg, entry_point, to_binary};
+use cw_storage_plus::Bound;
+use protobuf::Message;
+use seesaw::bank::ExecuteMsg as BankExecuteMsg;
+use seesaw::factory::{ConfigResponse, ExecuteMsg, InstantiateMsg, MarketResponse, MarketsResponse, QueryMsg};
+use seesaw::vamm::InstantiateMsg as VammInstantiateMsg;
+
+use crate::error::ContractError;
+use crate::response::MsgInstantiateContractResponse;
+use crate::state::{CONFIG, Config, MARKETS, Market, PENDING_MARKET, PendingMarket};
+
+const INSTANTIATE_REPLY_ID: u64 = 1;
+
+// Same page sizes as terraswap's factory
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+#[entry_point]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let config = Config {
+        owner_addr: deps.api.addr_canonicalize(info.sender.as_str())?,
+        bank_addr: deps.api.addr_canonicalize(deps.api.addr_validate(&msg.bank_addr)?.as_str())?,
+        vamm_code_id: msg.vamm_code_id,
+    };
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "instantiate")]))
+}
+
+#[entry_point]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::UpdateConfig { owner, vamm_code_id } => update_config(deps, info, owner, vamm_code_id),
+        ExecuteMsg::CreateMarket { market } => create_market(deps, env, info, *market),
+    }
+}
+
+fn assert_owner(deps: Deps, info: &MessageInfo) -> Result<Config, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(config)
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: Option<String>,
+    vamm_code_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut config = assert_owner(deps.as_ref(), &info)?;
+
+    if let Some(owner) = owner {
+        config.owner_addr = deps.api.addr_canonicalize(deps.api.addr_validate(&owner)?.as_str())?;
+    }
+
+    // Only markets created from now on use the new code
+    if let Some(vamm_code_id) = vamm_code_id {
+        config.vamm_code_id = vamm_code_id;
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![("action", "update_config")]))
+}
+
+// Instantiates a vAMM from the template, the reply registers it with the bank in the same transaction
+pub fn create_market(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    market: VammInstantiateMsg,
+) -> Result<Response, ContractError> {
+    let config = assert_owner(deps.as_ref(), &info)?;
+    let owner = deps.api.addr_humanize(&config.owner_addr)?.to_string();
+
+    let market = VammInstantiateMsg {
+        bank_addr: deps.api.addr_humanize(&config.bank_addr)?.to_string(),
+        owner: Some(owner.clone()),
+        ..market
+    };
+
+    PENDING_MARKET.save(deps.storage, &PendingMarket {
+        base_denom: market.base_denom.clone(),
+        stable_denom: market.stable_denom.clone(),
+    })?;
+
+    let label = format!("seesaw {}/{} market", market.base_denom, market.stable_denom);
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                admin: Some(owner), // Lets the factory owner migrate the market
+                code_id: config.vamm_code_id,
+                msg: to_binary(&market)?,
+                funds: vec![],
+                label,
+            },
+            INSTANTIATE_REPLY_ID,
+        ))
+        .add_attributes(vec![
+            ("action", "create_market"),
+            ("base_denom", market.base_denom.as_str()),
+            ("stable_denom", market.stable_denom.as_str()),
+        ])
+    )
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id != INSTANTIATE_REPLY_ID {
+        return Err(ContractError::Std(StdError::generic_err("Unknown reply id")));
+    }
+
+    let data = match msg.result {
+        ContractResult::Ok(response) => response.data
+            .ok_or_else(|| StdError::generic_err("Missing instantiate response data"))?,
+        ContractResult::Err(err) => return Err(ContractError::Std(StdError::generic_err(err))),
+    };
+
+    let res: MsgInstantiateContractResponse = Message::parse_from_bytes(data.as_slice())
+        .map_err(|_| StdError::parse_err("MsgInstantiateContractResponse", "failed to parse data"))?;
+    let market_addr = deps.api.addr_validate(res.get_contract_address())?;
+
+    let pending = PENDING_MARKET.load(deps.storage)?;
+    PENDING_MARKET.remove(deps.storage);
+
+    let contract_addr = deps.api.addr_canonicalize(market_addr.as_str())?;
+    if MARKETS.may_load(deps.storage, contract_addr.as_slice())?.is_some() {
+        return Err(ContractError::MarketExists {});
+    }
+
+    MARKETS.save(deps.storage, contract_addr.as_slice(), &Market {
+        contract_addr: contract_addr.clone(),
+        base_denom: pending.base_denom,
+        stable_denom: pending.stable_denom,
+    })?;
+
+    // Fails the whole transaction if the bank refuses the market
+    let config = CONFIG.load(deps.storage)?;
+    let register = WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&config.bank_addr)?.to_string(),
+        msg: to_binary(&BankExecuteMsg::RegisterMarket { contract_addr: market_addr.to_string() })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(register)
+        .add_attributes(vec![
+            ("action", "register_market"),
+            ("market_addr", market_addr.as_str()),
+        ])
+    )
+}
+
+#[entry_point]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Markets { start_after, limit } => to_binary(&query_markets(deps, start_after, limit)?),
+    }
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner_addr: deps.api.addr_humanize(&config.owner_addr)?,
+        bank_addr: deps.api.addr_humanize(&config.bank_addr)?,
+        vamm_code_id: config.vamm_code_id,
+    })
+}
+
+fn query_markets(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<MarketsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let start = match start_after {
+        Some(addr) => {
+            let canonical: CanonicalAddr = deps.api.addr_canonicalize(deps.api.addr_validate(&addr)?.as_str())?;
+            Some(Bound::exclusive(canonical.as_slice()))
+        }
+        None => None,
+    };
+
+    let markets = MARKETS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, market) = item?;
+            Ok(MarketResponse {
+                contract_addr: deps.api.addr_humanize(&market.contract_addr)?,
+                base_denom: market.base_denom,
+                stable_denom: market.stable_denom,
+            })
+        })
+        .collect::<StdResult<Vec<MarketResponse>>>()?;
+
+    Ok(MarketsResponse { markets })
+}
@@ -0,0 +1,7 @@
+pub mod contract;
+pub mod error;
+pub mod state;
+pub mod response;
+
+#[cfg(test)]
+pub mod testing;
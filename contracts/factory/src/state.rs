@@ -0,0 +1,34 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::CanonicalAddr;
+use cw_storage_plus::{Item, Map};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Config {
+    pub owner_addr: CanonicalAddr,
+    pub bank_addr: CanonicalAddr,
+    pub vamm_code_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Market {
+    pub contract_addr: CanonicalAddr,
+    pub base_denom: String,
+    pub stable_denom: String,
+}
+
+// Market being instantiated, completed with its address once the vAMM replies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PendingMarket {
+    pub base_denom: String,
+    pub stable_denom: String,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+pub const PENDING_MARKET: Item<PendingMarket> = Item::new("pending_market");
+
+pub const MARKETS: Map<&[u8], Market> = Map::new("markets");
@@ -0,0 +1,155 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Addr, Binary, ContractResult, CosmosMsg, DepsMut, Reply, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg, from_binary, to_binary};
+use protobuf::Message;
+use seesaw::bank::ExecuteMsg as BankExecuteMsg;
+use seesaw::factory::{ConfigResponse, ExecuteMsg, InstantiateMsg, MarketResponse, MarketsResponse, QueryMsg};
+use seesaw::vamm::{InstantiateMsg as VammInstantiateMsg, OracleConfig};
+
+use crate::contract::{execute, instantiate, query, reply};
+use crate::error::ContractError;
+use crate::response::MsgInstantiateContractResponse;
+
+fn market_template(base_denom: &str) -> VammInstantiateMsg {
+    VammInstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "anybank".to_string(),
+        base_denom: base_denom.to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    }
+}
+
+// What the chain returns to the factory once the vAMM is instantiated
+fn instantiate_reply(contract_addr: &str) -> Reply {
+    let mut response = MsgInstantiateContractResponse::new();
+    response.set_contract_address(contract_addr.to_string());
+
+    Reply {
+        id: 1,
+        result: ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: Some(Binary::from(response.write_to_bytes().unwrap())),
+        }),
+    }
+}
+
+fn create_market(mut deps: DepsMut, base_denom: &str, contract_addr: &str) {
+    let msg = ExecuteMsg::CreateMarket { market: Box::new(market_template(base_denom)) };
+    execute(deps.branch(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    reply(deps, mock_env(), instantiate_reply(contract_addr)).unwrap();
+}
+
+#[test]
+fn create_market_registers_with_bank() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg { vamm_code_id: 7, bank_addr: "bank0000".to_string() };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::CreateMarket { market: Box::new(market_template("uluna")) };
+    match execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // Markets point at the bank of the factory and are administered and migrated by its owner
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let expected = VammInstantiateMsg {
+        bank_addr: "bank0000".to_string(),
+        owner: Some("owner".to_string()),
+        ..market_template("uluna")
+    };
+    assert_eq!(res.messages, vec![SubMsg::reply_on_success(
+        WasmMsg::Instantiate {
+            admin: Some("owner".to_string()),
+            code_id: 7,
+            msg: to_binary(&expected).unwrap(),
+            funds: vec![],
+            label: "seesaw uluna/uusd market".to_string(),
+        },
+        1,
+    )]);
+
+    let res = reply(deps.as_mut(), mock_env(), instantiate_reply("market0000")).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "bank0000".to_string(),
+        msg: to_binary(&BankExecuteMsg::RegisterMarket { contract_addr: "market0000".to_string() }).unwrap(),
+        funds: vec![],
+    }))]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Markets { start_after: None, limit: None }).unwrap();
+    let markets: MarketsResponse = from_binary(&res).unwrap();
+    assert_eq!(markets.markets, vec![MarketResponse {
+        contract_addr: Addr::unchecked("market0000"),
+        base_denom: "uluna".to_string(),
+        stable_denom: "uusd".to_string(),
+    }]);
+
+    // A reply without a pending market is rejected
+    reply(deps.as_mut(), mock_env(), instantiate_reply("market0001")).unwrap_err();
+}
+
+#[test]
+fn list_markets() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg { vamm_code_id: 7, bank_addr: "bank0000".to_string() };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    create_market(deps.as_mut(), "uluna", "market0000");
+    create_market(deps.as_mut(), "ukrw", "market0001");
+    create_market(deps.as_mut(), "usdr", "market0002");
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Markets { start_after: None, limit: None }).unwrap();
+    let all: MarketsResponse = from_binary(&res).unwrap();
+    assert_eq!(all.markets.len(), 3);
+
+    // Pages follow each other without gaps or overlap
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Markets { start_after: None, limit: Some(2) }).unwrap();
+    let first: MarketsResponse = from_binary(&res).unwrap();
+    assert_eq!(first.markets, all.markets[..2].to_vec());
+
+    let start_after = first.markets[1].contract_addr.to_string();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Markets { start_after: Some(start_after), limit: Some(2) }).unwrap();
+    let second: MarketsResponse = from_binary(&res).unwrap();
+    assert_eq!(second.markets, all.markets[2..].to_vec());
+}
+
+#[test]
+fn update_config() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg { vamm_code_id: 7, bank_addr: "bank0000".to_string() };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig { owner: Some("owner0001".to_string()), vamm_code_id: Some(8) };
+    match execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config, ConfigResponse {
+        owner_addr: Addr::unchecked("owner0001"),
+        bank_addr: Addr::unchecked("bank0000"),
+        vamm_code_id: 8,
+    });
+}
@@ -0,0 +1,281 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult};
+use seesaw::bank::Sign;
+use seesaw::vamm::{OracleStatus, RepegResponse, SettlementSource, UpdateKResponse};
+
+use crate::contract::{accrue_funding, assert_market_open, get_market_price, get_twap_market_price, store_snapshot};
+use crate::error::ContractError;
+use crate::oracle::{check_oracle_price, get_underlying_price, query_oracle_price};
+use crate::state::{CONFIG, Config, CurveType, STATE, State};
+
+fn assert_owner(deps: Deps, info: &MessageInfo) -> Result<Config, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(config)
+}
+
+// Repeg and update k costs are worked out for x*y = k on quote asset units
+fn assert_constant_product(deps: Deps) -> Result<(), ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    if config.curve_type != CurveType::CONSTANT_PRODUCT {
+        return Err(ContractError::UnsupportedCurve {});
+    }
+
+    Ok(())
+}
+
+/*
+    REPEG
+*/
+
+// Moving the quote reserve changes what traders get back when they close.
+// If traders are net long N base, closing all positions pays out quote_reserve * N / (base_reserve + N),
+// if they are net short, they pay in quote_reserve * |N| / (base_reserve - |N|).
+// The cost to the system is the change of that amount.
+pub fn simulate_repeg(deps: Deps, env: &Env) -> Result<RepegResponse, ContractError> {
+    assert_constant_product(deps)?;
+    let state: State = STATE.load(deps.storage)?;
+
+    let target_price = get_underlying_price(deps, env)?;
+    let mark_price_before = get_market_price(deps)?;
+
+    let new_quote_reserve = state.base_asset_reserve * target_price;
+    if new_quote_reserve.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err("Repeg would empty the quote reserve")));
+    }
+
+    let net_position = Uint256::from(state.net_position_size.unsigned_abs());
+    let neutral_base_reserve = neutral_base_reserve(&state, state.base_asset_reserve)?;
+
+    let quote_reserve_change = if new_quote_reserve > state.quote_asset_reserve {
+        new_quote_reserve - state.quote_asset_reserve
+    } else {
+        state.quote_asset_reserve - new_quote_reserve
+    };
+
+    let cost = quote_reserve_change * Decimal256::from_ratio(net_position, neutral_base_reserve);
+
+    // Net longs gain from a higher quote reserve, net shorts from a lower one
+    let system_pays = (state.net_position_size > 0) == (new_quote_reserve > state.quote_asset_reserve);
+
+    Ok(RepegResponse {
+        base_asset_reserve: state.base_asset_reserve,
+        quote_asset_reserve: new_quote_reserve,
+        mark_price_before,
+        mark_price_after: Decimal256::from_ratio(new_quote_reserve, state.base_asset_reserve),
+        cost,
+        cost_sign: if system_pays && !cost.is_zero() { Sign::Positive } else { Sign::Negative }
+    })
+}
+
+pub fn repeg(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = assert_owner(deps.as_ref(), &info)?;
+
+    let repeg = simulate_repeg(deps.as_ref(), &env)?;
+
+    let mut state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
+    accrue_funding(deps.as_ref(), &env, &mut state)?;
+
+    if repeg.cost_sign == Sign::Positive {
+        if state.repeg_cost_spent + repeg.cost > config.repeg_budget {
+            return Err(ContractError::BudgetExceeded {});
+        }
+        state.repeg_cost_spent += repeg.cost;
+    }
+
+    state.quote_asset_reserve = repeg.quote_asset_reserve;
+    STATE.save(deps.storage, &state)?;
+
+    // Record the new reserves so TWAP and the circuit breaker see the jump
+    store_snapshot(deps.storage, &env, state.base_asset_reserve, state.quote_asset_reserve, 0i64)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "repeg"),
+        ("mark_price_before", repeg.mark_price_before.to_string().as_str()),
+        ("mark_price_after", repeg.mark_price_after.to_string().as_str()),
+        ("cost", repeg.cost.to_string().as_str()),
+    ]))
+}
+
+pub fn query_simulate_repeg(deps: Deps, env: Env) -> StdResult<RepegResponse> {
+    simulate_repeg(deps, &env).map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/*
+    UPDATE K
+*/
+
+// Base reserve once all positions are closed
+fn neutral_base_reserve(state: &State, base_asset_reserve: Uint256) -> StdResult<Uint256> {
+    let net_position = Uint256::from(state.net_position_size.unsigned_abs());
+
+    if state.net_position_size >= 0 {
+        Ok(base_asset_reserve + net_position)
+    } else if base_asset_reserve > net_position {
+        Ok(base_asset_reserve - net_position)
+    } else {
+        Err(StdError::generic_err("Base reserve too small for the net short position"))
+    }
+}
+
+// Quote asset traders get back (net long) or pay in (net short) when all positions are closed
+fn net_position_value(state: &State, base_asset_reserve: Uint256, quote_asset_reserve: Uint256) -> StdResult<Uint256> {
+    let net_position = Uint256::from(state.net_position_size.unsigned_abs());
+    let neutral_base_reserve = neutral_base_reserve(state, base_asset_reserve)?;
+
+    Ok(quote_asset_reserve * Decimal256::from_ratio(net_position, neutral_base_reserve))
+}
+
+// Scaling both reserves keeps the mark price but changes the slippage of closing the net position.
+// Deeper liquidity lets traders close at a better price, which the system pays for.
+pub fn simulate_update_k(deps: Deps, scale: Decimal256) -> Result<UpdateKResponse, ContractError> {
+    assert_constant_product(deps)?;
+    let state: State = STATE.load(deps.storage)?;
+
+    if scale.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    let new_base_reserve = state.base_asset_reserve * scale;
+    let new_quote_reserve = state.quote_asset_reserve * scale;
+    if new_base_reserve.is_zero() || new_quote_reserve.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err("Update would empty the reserves")));
+    }
+
+    let value_before = net_position_value(&state, state.base_asset_reserve, state.quote_asset_reserve)?;
+    let value_after = net_position_value(&state, new_base_reserve, new_quote_reserve)?;
+
+    // Longs gain when they get more back, shorts when they pay in less
+    let (cost, system_pays) = if value_after > value_before {
+        (value_after - value_before, state.net_position_size > 0)
+    } else {
+        (value_before - value_after, state.net_position_size < 0)
+    };
+
+    Ok(UpdateKResponse {
+        base_asset_reserve: new_base_reserve,
+        quote_asset_reserve: new_quote_reserve,
+        mark_price: Decimal256::from_ratio(new_quote_reserve, new_base_reserve),
+        cost,
+        cost_sign: if system_pays && !cost.is_zero() { Sign::Positive } else { Sign::Negative }
+    })
+}
+
+pub fn update_k(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    scale: Decimal256,
+) -> Result<Response, ContractError> {
+    let config = assert_owner(deps.as_ref(), &info)?;
+
+    let update = simulate_update_k(deps.as_ref(), scale)?;
+
+    let mut state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
+    accrue_funding(deps.as_ref(), &env, &mut state)?;
+
+    if update.cost_sign == Sign::Positive {
+        if state.update_k_cost_spent + update.cost > config.update_k_budget {
+            return Err(ContractError::BudgetExceeded {});
+        }
+        state.update_k_cost_spent += update.cost;
+    }
+
+    state.base_asset_reserve = update.base_asset_reserve;
+    state.quote_asset_reserve = update.quote_asset_reserve;
+    STATE.save(deps.storage, &state)?;
+
+    store_snapshot(deps.storage, &env, state.base_asset_reserve, state.quote_asset_reserve, 0i64)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_k"),
+        ("scale", scale.to_string().as_str()),
+        ("mark_price", update.mark_price.to_string().as_str()),
+        ("cost", update.cost.to_string().as_str()),
+        ("cost_sign", if update.cost_sign == Sign::Positive { "positive" } else { "negative" }),
+    ]))
+}
+
+pub fn query_simulate_update_k(deps: Deps, scale: Decimal256) -> StdResult<UpdateKResponse> {
+    simulate_update_k(deps, scale).map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/*
+    SHUTDOWN
+*/
+
+// Retires the market, trading stops and open positions settle at the price fixed here
+pub fn shutdown(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    price_source: SettlementSource,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info)?;
+
+    let mut state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
+
+    // Funding is owed up to the moment the market closes
+    accrue_funding(deps.as_ref(), &env, &mut state)?;
+
+    let settlement_price = match price_source {
+        SettlementSource::ORACLE => get_underlying_price(deps.as_ref(), &env)?,
+        SettlementSource::TWAP => {
+            get_twap_market_price(deps.as_ref(), &env, state.funding_period * Uint256::from(1_000_000u128))?
+        }
+    };
+
+    state.settlement_price = Some(settlement_price);
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "shutdown"),
+        ("settlement_price", settlement_price.to_string().as_str()),
+    ]))
+}
+
+/*
+    ORACLE RESET
+*/
+
+// The deviation guard compares every read with the previous one that passed it. After a larger move
+// between two reads it keeps tripping, until the owner accepts the new price as the reference.
+pub fn reset_oracle(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info)?;
+
+    let mut state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
+
+    // Accrue under the old reference before moving it
+    accrue_funding(deps.as_ref(), &env, &mut state)?;
+
+    // A stale price is not worth accepting
+    let oracle_price = query_oracle_price(deps.as_ref(), &env)?;
+    if check_oracle_price(deps.as_ref(), &env, &oracle_price)? == OracleStatus::Stale {
+        return Err(ContractError::StaleOracle {});
+    }
+
+    state.last_oracle_price = oracle_price.price;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "reset_oracle"),
+        ("oracle_price", oracle_price.price.to_string().as_str()),
+    ]))
+}
@@ -1,16 +1,21 @@
-use std::collections::VecDeque;
+use std::str::FromStr;
 use std::time;
 
-use crate::state::{CONFIG, Config, OracleType, STATE, State, SNAPSHOTS, MarketSnapshots, SnapshotItem};
+use crate::state::{CANDLES, CandleItem, CONFIG, Config, FUNDING_HISTORY, FundingSettlementItem, OracleType, STATE, State, SNAPSHOTS, SNAPSHOT_INDEX, SNAPSHOT_TIMES, SnapshotIndex, SnapshotItem};
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::{Addr, Binary, CanonicalAddr, Decimal, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Reply, ReplyOn, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg, attr, entry_point, from_binary, to_binary};
+use cosmwasm_std::{Addr, Binary, CanonicalAddr, Decimal, Deps, DepsMut, Env, MessageInfo, Order, QuerierWrapper, Reply, ReplyOn, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg, attr, entry_point, from_binary, to_binary};
+use cw_storage_plus::{Bound, U64Key};
 use cw20::Cw20ReceiveMsg;
 use seesaw::bank::Direction;
-use seesaw::vamm::{ConfigResponse, ExecuteMsg, Funding, InstantiateMsg, MarketItem, MarketsResponse, PositionResponse, QueryMsg, StateResponse, WhoPays};
-use terra_cosmwasm::{ExchangeRatesResponse, TerraQuerier};
+use seesaw::number::SignedDecimal256;
+use seesaw::vamm::{AmountKind, CandleResponse, CandlesResponse, ConfigResponse, ExecuteMsg, Funding, FundingHistoryResponse, FundingSettlementResponse, InstantiateMsg, MarketInfoResponse, MarketItem, MarketPriceResponse, MarketSnapshotsResponse, MarketsResponse, PositionResponse, QueryMsg, QuoteResponse, SimulationResponse, SnapshotResponse, StateResponse, WhoPays};
 use terraswap::asset::AssetInfo;
 
 use crate::error::ContractError;
+use crate::oracle::{get_underlying_price, oracle_config, oracle_source, query_oracle_price, query_oracle_status, query_underlying_price, validate_oracle_config};
+use crate::admin::{query_simulate_repeg, query_simulate_update_k, repeg, reset_oracle, shutdown, update_k};
+use crate::curve::{Curve, curve_config, market_curve, validate_curve_config};
+use crate::math::{checked_add, checked_sub, saturating_sub, to_i64};
 
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
@@ -21,79 +26,202 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    // Markets launched by the factory are administered by its owner
+    let owner_addr = match &msg.owner {
+        Some(owner) => deps.api.addr_validate(owner)?,
+        None => info.sender,
+    };
+
     let config = Config {
         contract_addr: deps.api.addr_canonicalize(&env.contract.address.as_str())?,
+        owner_addr: deps.api.addr_canonicalize(owner_addr.as_str())?,
         bank_addr: deps.api.addr_canonicalize(&msg.bank_addr.as_str())?,
+        oracle_type: validate_oracle_config(deps.as_ref(), &msg.oracle, &msg.base_denom, &msg.stable_denom, msg.max_oracle_price_age)?,
         stable_denom: msg.stable_denom,
-        oracle_type: OracleType::NATIVE,
-        base_denom: "uluna".to_string(),
+        base_denom: msg.base_denom,
+        max_oracle_price_age: msg.max_oracle_price_age,
+        max_oracle_price_deviation: msg.max_oracle_price_deviation,
+        snapshot_retention: msg.snapshot_retention,
+        toll_ratio: msg.toll_ratio,
+        spread_ratio: msg.spread_ratio,
+        max_trade_price_impact: msg.max_trade_price_impact,
+        max_block_price_impact: msg.max_block_price_impact,
+        repeg_budget: msg.repeg_budget,
+        update_k_budget: msg.update_k_budget,
+        max_funding_rate: msg.max_funding_rate,
+        init_base_reserve: Uint256::from(msg.init_base_reserve),
+        init_quote_reserve: Uint256::from(msg.init_quote_reserve),
+        max_long_open_interest: msg.max_long_open_interest,
+        max_short_open_interest: msg.max_short_open_interest,
+        curve_type: validate_curve_config(&msg.curve, msg.init_quote_reserve, msg.init_base_reserve)?,
     };
 
+    if config.toll_ratio + config.spread_ratio >= Decimal256::one() {
+        return Err(ContractError::Std(StdError::generic_err("Fees must be less than the notional traded")));
+    }
+
     CONFIG.save(deps.storage, &config)?;
 
     let funding_period: u128 = 8 * 60 * 60 * 1000; // Every 8 hours
 
+    // TWAP over a funding period needs the snapshots of the whole period
+    if u128::from(msg.snapshot_retention) * 1000 < funding_period {
+        return Err(ContractError::Std(StdError::generic_err("Snapshot retention must cover the funding period")));
+    }
+
     let state = State {
         base_asset_reserve: Uint256::from(msg.init_base_reserve), // Initialize at a certain price
         quote_asset_reserve: Uint256::from(msg.init_quote_reserve), // Initialize at a certain price
         funding_period: Uint256::from(funding_period), // Funding period in Nanoseconds
-        aggregated_funding: Decimal256::from_uint256(Uint256::from(1_000_000_000u128)),
+        aggregated_funding: SignedDecimal256::zero(),
         funding_rate: Funding {
             amount: Decimal256::zero(),
             who_pays: WhoPays::LONG
         },
-        last_funding_time: Uint256::zero()
+        last_funding_time: Uint256::from(env.block.time.nanos()), // First period ends one funding period after launch
+        last_accrual_time: Uint256::from(env.block.time.nanos()),
+        last_oracle_price: Decimal256::zero(),
+        net_position_size: 0i64,
+        total_long_size: Uint256::zero(),
+        total_short_size: Uint256::zero(),
+        repeg_cost_spent: Uint256::zero(),
+        update_k_cost_spent: Uint256::zero(),
+        settlement_price: None
     };
 
     STATE.save(deps.storage, &state)?;
 
-    let new_market_snapshots: MarketSnapshots = MarketSnapshots {
-        snapshots: vec! [
-            SnapshotItem {
-                base_asset_reserve: Uint256::from(msg.init_base_reserve), 
-                quote_asset_reserve: Uint256::from(msg.init_quote_reserve),
-                base_delta: 0i64,
-                timestamp: env.block.time.nanos()
-            }
-        ]
-    };
+    SNAPSHOTS.save(deps.storage, U64Key::from(0), &SnapshotItem {
+        base_asset_reserve: Uint256::from(msg.init_base_reserve),
+        quote_asset_reserve: Uint256::from(msg.init_quote_reserve),
+        base_delta: 0i64,
+        timestamp: env.block.time.nanos(),
+        cumulative_price: Decimal256::zero()
+    })?;
 
-    SNAPSHOTS.save(deps.storage, &new_market_snapshots)?;
-    
+    SNAPSHOT_INDEX.save(deps.storage, &SnapshotIndex { first: 0, next: 1 })?;
+    SNAPSHOT_TIMES.save(deps.storage, U64Key::from(env.block.time.nanos()), &0)?;
 
     Ok(Response::new().add_attributes(vec![("action", "instantiate")]))
 }
 
-fn  create_snapshots(
-    deps: Deps,
+// Max number of expired snapshots removed per swap, keeps the gas cost of a swap bounded
+const MAX_PRUNED_SNAPSHOTS: u64 = 10;
+
+// Candle resolutions kept up to date on every snapshot, in seconds
+const CANDLE_RESOLUTIONS: [u64; 6] = [60, 300, 900, 3_600, 14_400, 86_400];
+const MAX_CANDLES: usize = 500;
+
+pub fn store_snapshot(
+    storage: &mut dyn Storage,
     env: &Env,
     base_asset_reserve: Uint256,
-    quote_asset_reserve: Uint256, // Initialize at a certain price
+    quote_asset_reserve: Uint256,
     base_delta: i64,
-) -> StdResult<MarketSnapshots> {
-    let market_snapshots: MarketSnapshots = SNAPSHOTS.load(deps.storage)?;
-
-    let snapshot_items: Vec<SnapshotItem> = market_snapshots.snapshots;
-    let mut snapshot_deque = VecDeque::from(snapshot_items);
-
-    snapshot_deque.push_back(
-        SnapshotItem {
-            base_asset_reserve: base_asset_reserve, // Initialize at a certain price
-            quote_asset_reserve: quote_asset_reserve, // Initialize at a certain price
-            base_delta: base_delta,
-            timestamp: env.block.time.nanos()
+) -> StdResult<()> {
+    let config: Config = CONFIG.load(storage)?;
+    let mut index: SnapshotIndex = SNAPSHOT_INDEX.load(storage)?;
+
+    // The price of the previous snapshot held until now
+    let mut cumulative_price = Decimal256::zero();
+    if index.next > 0 {
+        let previous: SnapshotItem = SNAPSHOTS.load(storage, U64Key::from(index.next - 1))?;
+        update_candles(storage, env, &previous, base_asset_reserve, quote_asset_reserve, base_delta)?;
+
+        let previous_price = market_curve(&config.curve_type).mark_price(previous.quote_asset_reserve, previous.base_asset_reserve)?;
+        let held = env.block.time.nanos().saturating_sub(previous.timestamp);
+        cumulative_price = previous.cumulative_price + previous_price * Decimal256::from_uint256(held);
+    }
+
+    SNAPSHOTS.save(storage, U64Key::from(index.next), &SnapshotItem {
+        base_asset_reserve: base_asset_reserve,
+        quote_asset_reserve: quote_asset_reserve,
+        base_delta: base_delta,
+        timestamp: env.block.time.nanos(),
+        cumulative_price
+    })?;
+    SNAPSHOT_TIMES.save(storage, U64Key::from(env.block.time.nanos()), &index.next)?;
+    index.next += 1;
+
+    // Prune snapshots outside of the retention window, but keep the one that was live at its start
+    let cutoff = env.block.time.nanos().saturating_sub(config.snapshot_retention * 1_000_000_000);
+    let mut pruned: u64 = 0;
+    while pruned < MAX_PRUNED_SNAPSHOTS && index.first + 1 < index.next {
+        let following: SnapshotItem = SNAPSHOTS.load(storage, U64Key::from(index.first + 1))?;
+        if following.timestamp > cutoff {
+            break;
         }
-    );
 
-    let new_market_snapshots: MarketSnapshots = MarketSnapshots {
-        snapshots: Vec::from(snapshot_deque)
-    };
-    
-    Ok(new_market_snapshots)
+        // Snapshots of the same block share a timestamp, which points at the last of them
+        let pruned_snapshot: SnapshotItem = SNAPSHOTS.load(storage, U64Key::from(index.first))?;
+        if pruned_snapshot.timestamp < following.timestamp {
+            SNAPSHOT_TIMES.remove(storage, U64Key::from(pruned_snapshot.timestamp));
+        }
+
+        SNAPSHOTS.remove(storage, U64Key::from(index.first));
+        index.first += 1;
+        pruned += 1;
+    }
+
+    SNAPSHOT_INDEX.save(storage, &index)
 }
 
+// Fold a snapshot into the current bucket of every candle resolution
+fn update_candles(
+    storage: &mut dyn Storage,
+    env: &Env,
+    previous: &SnapshotItem,
+    base_asset_reserve: Uint256,
+    quote_asset_reserve: Uint256,
+    base_delta: i64,
+) -> StdResult<()> {
+    let config: Config = CONFIG.load(storage)?;
+    let curve = market_curve(&config.curve_type);
+
+    let open = curve.mark_price(previous.quote_asset_reserve, previous.base_asset_reserve)?;
+    let close = curve.mark_price(quote_asset_reserve, base_asset_reserve)?;
 
+    // Repegs and k changes move the price without trading
+    let quote_reserve_change = if quote_asset_reserve > previous.quote_asset_reserve {
+        quote_asset_reserve - previous.quote_asset_reserve
+    } else {
+        previous.quote_asset_reserve - quote_asset_reserve
+    };
+    let (base_volume, quote_volume) = if base_delta == 0 {
+        (Uint256::zero(), Uint256::zero())
+    } else {
+        (
+            Uint256::from(base_delta.unsigned_abs() as u128),
+            curve.reserve_to_quote(quote_reserve_change, false).map_err(|err| StdError::generic_err(err.to_string()))?
+        )
+    };
 
+    let now = env.block.time.seconds();
+    for resolution in CANDLE_RESOLUTIONS.iter() {
+        let key = (U64Key::from(*resolution), U64Key::from(now - now % resolution));
+        let candle = match CANDLES.may_load(storage, key.clone())? {
+            Some(candle) => CandleItem {
+                open: candle.open,
+                high: if close > candle.high { close } else { candle.high },
+                low: if close < candle.low { close } else { candle.low },
+                close,
+                base_volume: candle.base_volume + base_volume,
+                quote_volume: candle.quote_volume + quote_volume,
+            },
+            None => CandleItem {
+                open,
+                high: if close > open { close } else { open },
+                low: if close < open { close } else { open },
+                close,
+                base_volume,
+                quote_volume,
+            },
+        };
+        CANDLES.save(storage, key, &candle)?;
+    }
+
+    Ok(())
+}
 
 // And declare a custom Error variant for the ones where you will want to make use of it
 #[entry_point]
@@ -107,12 +235,32 @@ pub fn execute(
         ExecuteMsg::SwapIn {
             quote_asset_amount,
             direction,
-        } => swap_in(deps, env, info, quote_asset_amount, direction),
+            base_amount_limit,
+            belief_price,
+            max_spread,
+        } => swap_in(deps, env, info, quote_asset_amount, direction, base_amount_limit, belief_price, max_spread),
         ExecuteMsg::SwapOut {
             base_asset_amount,
             direction,
-        } => swap_out(deps, env, info, base_asset_amount, direction),
+            quote_amount_limit,
+            belief_price,
+            max_spread,
+        } => swap_out(deps, env, info, base_asset_amount, direction, quote_amount_limit, belief_price, max_spread),
+        ExecuteMsg::SwapInExactBase {
+            base_asset_amount,
+            direction,
+            max_quote,
+        } => swap_in_exact_base(deps, env, info, base_asset_amount, direction, max_quote),
+        ExecuteMsg::SwapOutExactQuote {
+            quote_asset_amount,
+            direction,
+            max_base,
+        } => swap_out_exact_quote(deps, env, info, quote_asset_amount, direction, max_base),
         ExecuteMsg::SettleFunding {} => settle_funding(deps, env, info),
+        ExecuteMsg::Repeg {} => repeg(deps, env, info),
+        ExecuteMsg::UpdateK { scale } => update_k(deps, env, info, scale),
+        ExecuteMsg::Shutdown { price_source } => shutdown(deps, env, info, price_source),
+        ExecuteMsg::ResetOracle {} => reset_oracle(deps, env, info),
     }
 }
 
@@ -133,22 +281,25 @@ pub fn settle_funding(
     }
 
     let state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
 
-    if state.last_funding_time + state.funding_period * Uint256::from(1_000_000u128) < Uint256::from(env.block.time.nanos()) {
+    if Uint256::from(env.block.time.nanos()) < state.last_funding_time + state.funding_period * Uint256::from(1_000_000u128) {
         return Err(ContractError::NotTime {})
     }
 
     let mut new_state = state.clone();
 
-    // Update last funding time
-    new_state.last_funding_time = state.last_funding_time + state.funding_period * Uint256::from(1_000_000u128);
-
-    let spot_price = get_underlying_price(deps.as_ref())?;
-    let mark_price = get_market_price(deps.as_ref())?;
+    // Bring the premium up to date before the reference oracle price moves
+    accrue_funding(deps.as_ref(), &env, &mut new_state)?;
 
-    let millis_day: u128 = 24 * 60 * 60 * 1000;
+    // Move to the latest period boundary, periods missed while nobody settled are not paid one by one
+    let period_nanos = u128::from(state.funding_period) * 1_000_000;
+    let elapsed = env.block.time.nanos() as u128 - u128::from(state.last_funding_time);
+    new_state.last_funding_time = state.last_funding_time + Uint256::from(elapsed / period_nanos * period_nanos);
 
-    // let premium: Decimal256 = spot_price - mark_price;
+    let spot_price = get_underlying_price(deps.as_ref(), &env)?;
+    // Average mark price over the funding period, so a single swap cannot swing the payment
+    let mark_price = get_twap_market_price(deps.as_ref(), &env, state.funding_period * Uint256::from(1_000_000u128))?;
 
     let (premium, who_pays) = if spot_price > mark_price {
         (spot_price - mark_price, WhoPays::SHORT)
@@ -156,32 +307,139 @@ pub fn settle_funding(
         (mark_price - spot_price, WhoPays::LONG)
     };
 
-    let premium_fraction: Decimal256 = premium * Decimal256::from_uint256(state.funding_period) / Decimal256::from_uint256(millis_day);
+    let mut premium_fraction: Decimal256 = premium * Decimal256::from_uint256(state.funding_period) / Decimal256::from_uint256(MILLIS_DAY);
 
-    new_state.aggregated_funding = match who_pays {
-        WhoPays::SHORT => {
-            new_state.aggregated_funding - premium_fraction
-        },
-        WhoPays::LONG => {
-            new_state.aggregated_funding + premium_fraction
-        }
-    };
+    // Cap the rate, so a thin market cannot drain positions in a single period
+    let max_premium_fraction = config.max_funding_rate * spot_price;
+    let clamped = !config.max_funding_rate.is_zero() && premium_fraction > max_premium_fraction;
+    if clamped {
+        premium_fraction = max_premium_fraction;
+    }
 
+    // Rate for the coming period, the premium itself accrues continuously
     new_state.funding_rate = Funding {
         amount: premium_fraction/spot_price,
-        who_pays: who_pays // SHORT PAY LONGS
+        who_pays: who_pays.clone() // SHORT PAY LONGS
     };
 
+    let period = (u128::from(new_state.last_funding_time) / period_nanos) as u64;
+    FUNDING_HISTORY.save(deps.storage, U64Key::new(period), &FundingSettlementItem {
+        timestamp: env.block.time.nanos(),
+        premium_fraction,
+        funding_rate: new_state.funding_rate.amount,
+        mark_price,
+        index_price: spot_price,
+        who_pays
+    })?;
+
+    new_state.last_oracle_price = spot_price;
+
     STATE.save(deps.storage, &new_state)?;
 
-    Ok(Response::default())
+    Ok(Response::new().add_attributes(vec![
+        ("action", "settle_funding"),
+        ("funding_rate", new_state.funding_rate.amount.to_string().as_str()),
+        ("funding_rate_clamped", clamped.to_string().as_str()),
+    ]))
 }
 
-fn get_market_price(deps: Deps) -> StdResult<Decimal256> {
+const MILLIS_DAY: u128 = 24 * 60 * 60 * 1000;
+
+// Accrue the premium between mark and oracle price since the last accrual into aggregated_funding.
+// Every change of the reserves accrues first, so the mark price is constant over the accrued interval.
+pub fn accrue_funding(deps: Deps, env: &Env, state: &mut State) -> StdResult<()> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    // Funding stops at shutdown
+    let now = Uint256::from(env.block.time.nanos());
+    if now <= state.last_accrual_time || state.settlement_price.is_some() {
+        return Ok(());
+    }
+
+    // Without a trusted oracle price there is no premium, the outage is skipped rather than
+    // charged later at whatever the mark price is when the oracle recovers. A deviation keeps
+    // the old reference, so accrual only resumes once the owner accepts the price with ResetOracle
+    let spot_price = match get_underlying_price(deps, env) {
+        Ok(price) => price,
+        Err(ContractError::StaleOracle {}) | Err(ContractError::OracleDeviation {}) => {
+            state.last_accrual_time = now;
+            return Ok(());
+        }
+        Err(ContractError::Std(err)) => return Err(err),
+        Err(err) => return Err(StdError::generic_err(err.to_string())),
+    };
+    let mark_price = market_curve(&config.curve_type).mark_price(state.quote_asset_reserve, state.base_asset_reserve)?;
+
+    let elapsed_millis = Decimal256::from_uint256(now - state.last_accrual_time) / Decimal256::from_uint256(1_000_000u128);
+
+    // Positive when longs pay shorts
+    let premium = SignedDecimal256::from(mark_price) - SignedDecimal256::from(spot_price);
+
+    let mut premium_fraction = premium * elapsed_millis / Decimal256::from_uint256(MILLIS_DAY);
+
+    // Funding rate cap is per funding period, scale it to the accrued interval
+    if !config.max_funding_rate.is_zero() {
+        let max_premium_fraction = config.max_funding_rate * spot_price * elapsed_millis / Decimal256::from_uint256(state.funding_period);
+        if premium_fraction.abs() > max_premium_fraction {
+            premium_fraction = SignedDecimal256::new(max_premium_fraction, premium_fraction.is_negative());
+        }
+    }
+
+    state.aggregated_funding += premium_fraction;
+    state.last_accrual_time = now;
+    state.last_oracle_price = spot_price;
+
+    Ok(())
+}
+
+pub fn get_market_price(deps: Deps) -> StdResult<Decimal256> {
+    let config: Config = CONFIG.load(deps.storage)?;
     let state: State = STATE.load(deps.storage)?;
-    let mark_price: Decimal256 = Decimal256::from_uint256(state.quote_asset_reserve)
-        / Decimal256::from_uint256(state.base_asset_reserve);
-    Ok(mark_price)
+    market_curve(&config.curve_type).mark_price(state.quote_asset_reserve, state.base_asset_reserve)
+}
+
+// Time weighted average of the mark price over the last `interval` nanoseconds. As in Uniswap v2,
+// it is the change of the cumulative price between both ends, so it costs the same however many swaps there were.
+pub fn get_twap_market_price(deps: Deps, env: &Env, interval: Uint256) -> StdResult<Decimal256> {
+    let index: SnapshotIndex = SNAPSHOT_INDEX.load(deps.storage)?;
+
+    let now = env.block.time.nanos();
+    let mut interval_start = if Uint256::from(now) > interval { now - u128::from(interval) as u64 } else { 0 };
+
+    // Snapshot live at the start of the interval, or the oldest one kept if the interval reaches back further
+    let start_index = SNAPSHOT_TIMES
+        .range(deps.storage, None, Some(Bound::inclusive_int(interval_start)), Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, snapshot_index)| snapshot_index)
+        .filter(|snapshot_index| *snapshot_index >= index.first);
+    let start_snapshot: SnapshotItem = match start_index {
+        Some(snapshot_index) => SNAPSHOTS.load(deps.storage, U64Key::from(snapshot_index))?,
+        None => {
+            let oldest: SnapshotItem = SNAPSHOTS.load(deps.storage, U64Key::from(index.first))?;
+            interval_start = oldest.timestamp.max(interval_start);
+            oldest
+        }
+    };
+    let latest_snapshot: SnapshotItem = SNAPSHOTS.load(deps.storage, U64Key::from(index.next - 1))?;
+
+    // No time has passed since the last snapshot, use the current mark price
+    if now <= interval_start {
+        return get_market_price(deps);
+    }
+
+    let cumulative_price_start = cumulative_price_at(deps, &start_snapshot, interval_start)?;
+    let cumulative_price_now = cumulative_price_at(deps, &latest_snapshot, now)?;
+
+    Ok((cumulative_price_now - cumulative_price_start) / Decimal256::from_uint256(now - interval_start))
+}
+
+// Cumulative price at a time after the snapshot, its price holding since it was taken
+fn cumulative_price_at(deps: Deps, snapshot: &SnapshotItem, time: u64) -> StdResult<Decimal256> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let price = market_curve(&config.curve_type).mark_price(snapshot.quote_asset_reserve, snapshot.base_asset_reserve)?;
+
+    Ok(snapshot.cumulative_price + price * Decimal256::from_uint256(time.saturating_sub(snapshot.timestamp)))
 }
 
 /*
@@ -194,36 +452,103 @@ pub fn swap_in(
     info: MessageInfo,
     quote_asset_amount: Uint256,
     direction: Direction,
+    base_amount_limit: Option<Uint256>,
+    belief_price: Option<Decimal256>,
+    max_spread: Option<Decimal256>,
 ) -> Result<Response, ContractError> {
+    assert_bank(deps.as_ref(), &info)?;
+
     // Get amount of base we will be long/short
     // LONG -> how much base asset returned when we open position
     // SHORT -> how much base asset we borrow when we open position
     let base_amount = simulate_swapin(deps.as_ref(), quote_asset_amount, &direction)?;
 
-    let state: State = STATE.load(deps.storage)?;
+    // Rounding goes the market's way, a tiny trade can be worth no base at all
+    if base_amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    // Longs buy base assets, shorts sell borrowed base assets
+    let buying_base = direction == Direction::LONG;
+
+    if let Some(limit) = base_amount_limit {
+        if (buying_base && base_amount < limit) || (!buying_base && base_amount > limit) {
+            return Err(ContractError::MaxSlippage {});
+        }
+    }
+
+    assert_max_spread(get_market_price(deps.as_ref())?, quote_asset_amount, base_amount, buying_base, belief_price, max_spread)?;
+
+    apply_swap_in(deps, env, quote_asset_amount, base_amount, direction)
+}
+
+// Opens a position of an exact base size, pricing it as the reverse of a close
+pub fn swap_in_exact_base(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    base_asset_amount: Uint256,
+    direction: Direction,
+    max_quote: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    assert_bank(deps.as_ref(), &info)?;
+
+    let quote_asset_amount = simulate_swapin_exact_base(deps.as_ref(), base_asset_amount, &direction)?;
+
+    if quote_asset_amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    if let Some(limit) = max_quote {
+        if (direction == Direction::LONG && quote_asset_amount > limit) || (direction == Direction::SHORT && quote_asset_amount < limit) {
+            return Err(ContractError::MaxSlippage {});
+        }
+    }
+
+    apply_swap_in(deps, env, quote_asset_amount, base_asset_amount, direction)
+}
+
+fn apply_swap_in(
+    deps: DepsMut,
+    env: Env,
+    quote_asset_amount: Uint256,
+    base_amount: Uint256,
+    direction: Direction,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let curve = market_curve(&config.curve_type);
+
+    let mut state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
+
+    // Accrue funding at the mark price before the trade
+    accrue_funding(deps.as_ref(), &env, &mut state)?;
 
     let mut new_state = state.clone();
 
     match direction {
         Direction::LONG => {
-            new_state.quote_asset_reserve += quote_asset_amount; // Send UST into market
-            new_state.base_asset_reserve = state.base_asset_reserve - base_amount;
+            new_state.quote_asset_reserve = checked_add(state.quote_asset_reserve, curve.quote_to_reserve(quote_asset_amount, false)?)?; // Send UST into market
+            new_state.base_asset_reserve = checked_sub(state.base_asset_reserve, base_amount)
+                .map_err(|_| ContractError::InsufficientReserve {})?;
 
-            let delta : i64 = u128::from(Uint128::from(base_amount)) as i64; // Positive delta if long
+            let delta : i64 = to_i64(base_amount)?; // Positive delta if long
 
-            let new_market_snapshots = create_snapshots(deps.as_ref(), &env, new_state.quote_asset_reserve, new_state.base_asset_reserve, delta)?;
-            SNAPSHOTS.save(deps.storage, &new_market_snapshots)?;
+            new_state.net_position_size = state.net_position_size.checked_add(delta).ok_or(ContractError::Overflow {})?;
+            new_state.total_long_size = checked_add(state.total_long_size, base_amount)?;
+            store_snapshot(deps.storage, &env, new_state.base_asset_reserve, new_state.quote_asset_reserve, delta)?;
         }
         Direction::SHORT => {
-            new_state.base_asset_reserve += base_amount; // Sell borrowed base assets to market
-            new_state.quote_asset_reserve = state.quote_asset_reserve - quote_asset_amount;
+            new_state.base_asset_reserve = checked_add(state.base_asset_reserve, base_amount)?; // Sell borrowed base assets to market
+            new_state.quote_asset_reserve = checked_sub(state.quote_asset_reserve, curve.quote_to_reserve(quote_asset_amount, true)?)
+                .map_err(|_| ContractError::InsufficientReserve {})?;
             // Take out UST from market
 
-            let mut delta : i64 = u128::from(Uint128::from(base_amount)) as i64;
-            delta = -delta; // Negative delta if short
+            let delta : i64 = -to_i64(base_amount)?; // Negative delta if short
 
-            let new_market_snapshots = create_snapshots(deps.as_ref(), &env, new_state.quote_asset_reserve, new_state.base_asset_reserve, delta)?;
-            SNAPSHOTS.save(deps.storage, &new_market_snapshots)?;
+            new_state.net_position_size = state.net_position_size.checked_add(delta).ok_or(ContractError::Overflow {})?;
+            new_state.total_short_size = checked_add(state.total_short_size, base_amount)?;
+            store_snapshot(deps.storage, &env, new_state.base_asset_reserve, new_state.quote_asset_reserve, delta)?;
         }
         Direction::NOT_SET => {
             return Err(ContractError::Std(
@@ -232,9 +557,19 @@ pub fn swap_in(
         }
     }
 
+    assert_k_not_decreased(curve.as_ref(), &state, &new_state)?;
+    assert_open_interest(deps.as_ref(), &new_state)?;
+    assert_price_impact(deps.as_ref(), &env, &state, &new_state)?;
+
     STATE.save(deps.storage, &new_state)?;
 
-    Ok(Response::new().add_attributes(vec![("action", "swap")]))
+    let (toll_fee, spread_fee) = compute_fees(deps.as_ref(), quote_asset_amount)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "swap"),
+        ("toll_fee", toll_fee.to_string().as_str()),
+        ("spread_fee", spread_fee.to_string().as_str())
+    ]))
 }
 
 pub fn swap_out(
@@ -243,38 +578,104 @@ pub fn swap_out(
     info: MessageInfo,
     base_asset_amount: Uint256,
     direction: Direction,
+    quote_amount_limit: Option<Uint256>,
+    belief_price: Option<Decimal256>,
+    max_spread: Option<Decimal256>,
 ) -> Result<Response, ContractError> {
+    assert_bank(deps.as_ref(), &info)?;
+
     // Get amount of base we will be long/short
     // LONG -> how much base asset returned when we open position
     // SHORT -> how much base asset we borrow when we open position
     let quote_asset_amount = simulate_swapout(deps.as_ref(), base_asset_amount, &direction)?;
 
-    let state: State = STATE.load(deps.storage)?;
+    if quote_asset_amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    // Longs sell their base assets, shorts buy base assets back
+    let buying_base = direction == Direction::SHORT;
+
+    if let Some(limit) = quote_amount_limit {
+        if (buying_base && quote_asset_amount > limit) || (!buying_base && quote_asset_amount < limit) {
+            return Err(ContractError::MaxSlippage {});
+        }
+    }
+
+    assert_max_spread(get_market_price(deps.as_ref())?, quote_asset_amount, base_asset_amount, buying_base, belief_price, max_spread)?;
+
+    apply_swap_out(deps, env, quote_asset_amount, base_asset_amount, direction)
+}
+
+// Closes the part of a position worth an exact notional
+pub fn swap_out_exact_quote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    quote_asset_amount: Uint256,
+    direction: Direction,
+    max_base: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    assert_bank(deps.as_ref(), &info)?;
+
+    let base_asset_amount = simulate_swapout_exact_quote(deps.as_ref(), quote_asset_amount, &direction)?;
+
+    if base_asset_amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    if let Some(limit) = max_base {
+        if (direction == Direction::LONG && base_asset_amount > limit) || (direction == Direction::SHORT && base_asset_amount < limit) {
+            return Err(ContractError::MaxSlippage {});
+        }
+    }
+
+    apply_swap_out(deps, env, quote_asset_amount, base_asset_amount, direction)
+}
+
+fn apply_swap_out(
+    deps: DepsMut,
+    env: Env,
+    quote_asset_amount: Uint256,
+    base_asset_amount: Uint256,
+    direction: Direction,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let curve = market_curve(&config.curve_type);
+
+    let mut state: State = STATE.load(deps.storage)?;
+    assert_market_open(&state)?;
+
+    // Accrue funding at the mark price before the trade
+    accrue_funding(deps.as_ref(), &env, &mut state)?;
 
     let mut new_state = state.clone();
 
     match direction {
         Direction::LONG => {
-            new_state.base_asset_reserve += base_asset_amount; // Sell base assets to market
-            new_state.quote_asset_reserve = state.quote_asset_reserve - quote_asset_amount;
+            new_state.base_asset_reserve = checked_add(state.base_asset_reserve, base_asset_amount)?; // Sell base assets to market
+            new_state.quote_asset_reserve = checked_sub(state.quote_asset_reserve, curve.quote_to_reserve(quote_asset_amount, true)?)
+                .map_err(|_| ContractError::InsufficientReserve {})?;
             // Get UST back
 
-            let mut delta : i64 = u128::from(Uint128::from(base_asset_amount)) as i64;
-            delta = -delta; // Negative delta on closing if long
+            let delta : i64 = -to_i64(base_asset_amount)?; // Negative delta on closing if long
 
-            let new_market_snapshots = create_snapshots(deps.as_ref(), &env, new_state.quote_asset_reserve, new_state.base_asset_reserve, delta)?;
-            SNAPSHOTS.save(deps.storage, &new_market_snapshots)?;
+            new_state.net_position_size = state.net_position_size.checked_add(delta).ok_or(ContractError::Overflow {})?;
+            new_state.total_long_size = reduce_open_interest(state.total_long_size, base_asset_amount);
+            store_snapshot(deps.storage, &env, new_state.base_asset_reserve, new_state.quote_asset_reserve, delta)?;
 
         }
         Direction::SHORT => {
-            new_state.quote_asset_reserve += quote_asset_amount; // Send UST into market
-            new_state.base_asset_reserve = state.base_asset_reserve - base_asset_amount;
+            new_state.quote_asset_reserve = checked_add(state.quote_asset_reserve, curve.quote_to_reserve(quote_asset_amount, false)?)?; // Send UST into market
+            new_state.base_asset_reserve = checked_sub(state.base_asset_reserve, base_asset_amount)
+                .map_err(|_| ContractError::InsufficientReserve {})?;
             // Buy base assets to return
 
-            let mut delta : i64 = u128::from(Uint128::from(base_asset_amount)) as i64; // Positive delta on closing if short
+            let delta : i64 = to_i64(base_asset_amount)?; // Positive delta on closing if short
 
-            let new_market_snapshots = create_snapshots(deps.as_ref(), &env, new_state.quote_asset_reserve, new_state.base_asset_reserve, delta)?;
-            SNAPSHOTS.save(deps.storage, &new_market_snapshots)?;
+            new_state.net_position_size = state.net_position_size.checked_add(delta).ok_or(ContractError::Overflow {})?;
+            new_state.total_short_size = reduce_open_interest(state.total_short_size, base_asset_amount);
+            store_snapshot(deps.storage, &env, new_state.base_asset_reserve, new_state.quote_asset_reserve, delta)?;
 
         }
         Direction::NOT_SET => {
@@ -284,27 +685,190 @@ pub fn swap_out(
         }
     }
 
+    assert_k_not_decreased(curve.as_ref(), &state, &new_state)?;
+    assert_price_impact(deps.as_ref(), &env, &state, &new_state)?;
+
     STATE.save(deps.storage, &new_state)?;
 
-    Ok(Response::new().add_attributes(vec![("action", "swap")]))
+    let (toll_fee, spread_fee) = compute_fees(deps.as_ref(), quote_asset_amount)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "swap"),
+        ("toll_fee", toll_fee.to_string().as_str()),
+        ("spread_fee", spread_fee.to_string().as_str())
+    ]))
+}
+
+const DEFAULT_SLIPPAGE: &str = "0.005";
+
+// Same rules as terraswap's pair: with a belief price, the execution price is checked against it
+// (default max spread 0.5%), with only a max spread, against the mark price before the trade
+fn assert_max_spread(
+    mark_price: Decimal256,
+    quote_asset_amount: Uint256,
+    base_asset_amount: Uint256,
+    buying_base: bool,
+    belief_price: Option<Decimal256>,
+    max_spread: Option<Decimal256>,
+) -> Result<(), ContractError> {
+    let (expected_price, max_spread) = match (belief_price, max_spread) {
+        (Some(belief_price), max_spread) => (belief_price, max_spread.unwrap_or(Decimal256::from_str(DEFAULT_SLIPPAGE)?)),
+        (None, Some(max_spread)) => (mark_price, max_spread),
+        (None, None) => return Ok(()),
+    };
+
+    if base_asset_amount.is_zero() || expected_price.is_zero() {
+        return Err(ContractError::MaxSlippage {});
+    }
+
+    let execution_price = Decimal256::from_ratio(quote_asset_amount, base_asset_amount);
+
+    // Buyers lose when the price is higher than expected, sellers when it is lower
+    let spread = if buying_base && execution_price > expected_price {
+        (execution_price - expected_price) / expected_price
+    } else if !buying_base && execution_price < expected_price {
+        (expected_price - execution_price) / expected_price
+    } else {
+        Decimal256::zero()
+    };
+
+    if spread > max_spread {
+        return Err(ContractError::MaxSlippage {});
+    }
+
+    Ok(())
+}
+
+// Positions are only opened and closed through the bank, which holds the margin
+fn assert_bank(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.bank_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+pub fn assert_market_open(state: &State) -> Result<(), ContractError> {
+    if state.settlement_price.is_some() {
+        return Err(ContractError::MarketClosed {});
+    }
+    Ok(())
+}
+
+// Rounding always goes the market's way, a swap can only grow the invariant of the curve
+fn assert_k_not_decreased(curve: &dyn Curve, state: &State, new_state: &State) -> Result<(), ContractError> {
+    let k = curve.invariant(state.quote_asset_reserve, state.base_asset_reserve)?;
+    let new_k = curve.invariant(new_state.quote_asset_reserve, new_state.base_asset_reserve)?;
+
+    if new_k < k {
+        return Err(ContractError::InvariantViolation {});
+    }
+
+    Ok(())
+}
+
+fn price_change(from: Decimal256, to: Decimal256) -> Decimal256 {
+    if to > from {
+        (to - from) / from
+    } else {
+        (from - to) / from
+    }
+}
+
+// Circuit breaker, a swap cannot move the mark price more than the configured limits,
+// neither on its own nor together with the swaps before it in the same block
+fn assert_price_impact(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    new_state: &State,
+) -> Result<(), ContractError> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let curve = market_curve(&config.curve_type);
+
+    let price_before = curve.mark_price(state.quote_asset_reserve, state.base_asset_reserve)?;
+    let price_after = curve.mark_price(new_state.quote_asset_reserve, new_state.base_asset_reserve)?;
+
+    if !config.max_trade_price_impact.is_zero()
+        && price_change(price_before, price_after) > config.max_trade_price_impact {
+        return Err(ContractError::MaxPriceImpact {});
+    }
+
+    if !config.max_block_price_impact.is_zero() {
+        // Mark price at the start of the block is the one of the last snapshot taken before it
+        let now = env.block.time.nanos();
+        let mut block_start_snapshot: Option<SnapshotItem> = None;
+        for item in SNAPSHOTS.range(deps.storage, None, None, Order::Descending) {
+            let (_, snapshot) = item?;
+            let taken_before_block = snapshot.timestamp < now;
+            block_start_snapshot = Some(snapshot);
+            if taken_before_block {
+                break;
+            }
+        }
+
+        if let Some(snapshot) = block_start_snapshot {
+            let block_start_price = curve.mark_price(snapshot.quote_asset_reserve, snapshot.base_asset_reserve)?;
+            if price_change(block_start_price, price_after) > config.max_block_price_impact {
+                return Err(ContractError::MaxPriceImpact {});
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Toll and spread fees charged on the quote notional of a trade
+fn compute_fees(deps: Deps, quote_asset_amount: Uint256) -> StdResult<(Uint256, Uint256)> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    Ok((quote_asset_amount * config.toll_ratio, quote_asset_amount * config.spread_ratio))
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::SimulateIn {
             quoteAmount,
             direction,
-        } => to_binary(&simulate_swapin(deps, quoteAmount, &direction)?),
+        } => to_binary(&query_simulate_in(deps, quoteAmount, direction)?),
         QueryMsg::SimulateOut {
             baseAmount,
             direction,
-        } => to_binary(&simulate_swapout(deps, baseAmount, &direction)?),
-        QueryMsg::OraclePrice {} => to_binary(&query_config(deps)?),
-        QueryMsg::MarketPrice {} => to_binary(&query_config(deps)?),
-        QueryMsg::State {} => to_binary(&query_state(deps)?),
-        QueryMsg::MarketInfo {} => to_binary(&query_state(deps)?),
-        QueryMsg::MarketSnapshots {} => to_binary(&get_market_snapshots(deps)?),
+        } => to_binary(&query_simulate_out(deps, baseAmount, direction)?),
+        QueryMsg::SimulateInExactBase {
+            base_asset_amount,
+            direction,
+        } => to_binary(&query_simulate_in_exact_base(deps, base_asset_amount, direction)?),
+        QueryMsg::SimulateOutExactQuote {
+            quote_asset_amount,
+            direction,
+        } => to_binary(&query_simulate_out_exact_quote(deps, quote_asset_amount, direction)?),
+        QueryMsg::Quote {
+            side,
+            amount,
+            amount_kind,
+        } => to_binary(&query_quote(deps, side, amount, amount_kind)?),
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::OraclePrice {} => to_binary(&query_underlying_price(deps, &env)?),
+        QueryMsg::MarketPrice {} => to_binary(&query_market_price(deps, env)?),
+        QueryMsg::State {} => to_binary(&query_state(deps, env)?),
+        QueryMsg::MarketInfo {} => to_binary(&query_market_info(deps)?),
+        QueryMsg::MarketSnapshots { start_after, limit, start_time, end_time } => {
+            to_binary(&query_market_snapshots(deps, start_after, limit, start_time, end_time)?)
+        },
+        QueryMsg::FundingHistory { start_after, limit } => {
+            to_binary(&query_funding_history(deps, start_after, limit)?)
+        },
+        QueryMsg::SimulateRepeg {} => to_binary(&query_simulate_repeg(deps, env)?),
+        QueryMsg::SimulateUpdateK { scale } => to_binary(&query_simulate_update_k(deps, scale)?),
+        QueryMsg::TwapMarkPrice { interval_seconds } => {
+            to_binary(&get_twap_market_price(deps, &env, Uint256::from(interval_seconds) * Uint256::from(1_000_000_000u128))?)
+        },
+        QueryMsg::OracleStatus {} => to_binary(&query_oracle_status(deps, &env)?),
+        QueryMsg::Candles { resolution_seconds, start, end } => {
+            to_binary(&query_candles(deps, resolution_seconds, start, end)?)
+        },
     }
 }
 
@@ -312,24 +876,288 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     ORACLE FUNCTIONS
 */
 
-// Get the price of underlying asset
-pub fn get_market_snapshots(deps: Deps) -> StdResult<MarketSnapshots> {
-    let market_snapshots = SNAPSHOTS.load(deps.storage)?;
+// Limits for paginated queries
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+pub fn query_market_snapshots(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+) -> StdResult<MarketSnapshotsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // First snapshot after the last one taken before start_time
+    let time_start = match start_time {
+        Some(start_time) => SNAPSHOT_TIMES
+            .range(deps.storage, None, Some(Bound::exclusive_int(start_time)), Order::Descending)
+            .next()
+            .transpose()?
+            .map(|(_, snapshot_index)| snapshot_index + 1),
+        None => None,
+    };
+    let start = start_after.map(|index| index.saturating_add(1)).max(time_start).map(Bound::inclusive_int);
+
+    let end_time = end_time.unwrap_or(u64::MAX);
+
+    // Snapshots are stored in time order, so stop at the first one after end_time
+    let snapshots: Vec<SnapshotResponse> = SNAPSHOTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .map(|item| {
+            let (key, snapshot) = item?;
+            let mut index_bytes = [0u8; 8];
+            index_bytes.copy_from_slice(&key);
+
+            Ok(SnapshotResponse {
+                index: u64::from_be_bytes(index_bytes),
+                base_asset_reserve: snapshot.base_asset_reserve,
+                quote_asset_reserve: snapshot.quote_asset_reserve,
+                base_delta: snapshot.base_delta,
+                timestamp: snapshot.timestamp
+            })
+        })
+        .take_while(|snapshot: &StdResult<SnapshotResponse>| match snapshot {
+            Ok(snapshot) => snapshot.timestamp <= end_time,
+            Err(_) => true,
+        })
+        .take(limit)
+        .collect::<StdResult<Vec<SnapshotResponse>>>()?;
+
+    Ok(MarketSnapshotsResponse { snapshots })
+}
+
+// Candles with a bucket start in [start, end), the bucket containing start included
+pub fn query_candles(
+    deps: Deps,
+    resolution_seconds: u64,
+    start: u64,
+    end: u64,
+) -> StdResult<CandlesResponse> {
+    if !CANDLE_RESOLUTIONS.contains(&resolution_seconds) {
+        return Err(StdError::generic_err("Unsupported candle resolution"));
+    }
 
-    return Ok(market_snapshots);
+    let candles: Vec<CandleResponse> = CANDLES
+        .prefix(U64Key::from(resolution_seconds))
+        .range(
+            deps.storage,
+            Some(Bound::inclusive_int(start - start % resolution_seconds)),
+            Some(Bound::exclusive_int(end)),
+            Order::Ascending,
+        )
+        .take(MAX_CANDLES)
+        .map(|item| {
+            let (key, candle) = item?;
+            let mut start_bytes = [0u8; 8];
+            start_bytes.copy_from_slice(&key);
+
+            Ok(CandleResponse {
+                start: u64::from_be_bytes(start_bytes),
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+                base_volume: candle.base_volume,
+                quote_volume: candle.quote_volume
+            })
+        })
+        .collect::<StdResult<Vec<CandleResponse>>>()?;
+
+    Ok(CandlesResponse { candles })
+}
+
+pub fn query_funding_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<FundingHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+
+    let settlements: Vec<FundingSettlementResponse> = FUNDING_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, settlement) = item?;
+            let mut period_bytes = [0u8; 8];
+            period_bytes.copy_from_slice(&key);
+
+            Ok(FundingSettlementResponse {
+                period: u64::from_be_bytes(period_bytes),
+                timestamp: settlement.timestamp,
+                premium_fraction: settlement.premium_fraction,
+                funding_rate: settlement.funding_rate,
+                mark_price: settlement.mark_price,
+                index_price: settlement.index_price,
+                who_pays: settlement.who_pays
+            })
+        })
+        .collect::<StdResult<Vec<FundingSettlementResponse>>>()?;
+
+    Ok(FundingHistoryResponse { settlements })
 }
 
 /*
     AMM SIMULATION FUNCTIONS
 */
 
+fn query_simulate_in(
+    deps: Deps,
+    quote_asset_amount: Uint256,
+    direction: Direction,
+) -> StdResult<SimulationResponse> {
+    let base_asset_amount = simulate_swapin(deps, quote_asset_amount, &direction)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let (toll_fee, spread_fee) = compute_fees(deps, quote_asset_amount)?;
+
+    Ok(SimulationResponse {
+        return_amount: base_asset_amount,
+        toll_fee,
+        spread_fee
+    })
+}
+
+fn query_simulate_out(
+    deps: Deps,
+    base_asset_amount: Uint256,
+    direction: Direction,
+) -> StdResult<SimulationResponse> {
+    let quote_asset_amount = simulate_swapout(deps, base_asset_amount, &direction)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let (toll_fee, spread_fee) = compute_fees(deps, quote_asset_amount)?;
+
+    Ok(SimulationResponse {
+        return_amount: quote_asset_amount,
+        toll_fee,
+        spread_fee
+    })
+}
+
+fn query_simulate_in_exact_base(
+    deps: Deps,
+    base_asset_amount: Uint256,
+    direction: Direction,
+) -> StdResult<SimulationResponse> {
+    let quote_asset_amount = simulate_swapin_exact_base(deps, base_asset_amount, &direction)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let (toll_fee, spread_fee) = compute_fees(deps, quote_asset_amount)?;
+
+    Ok(SimulationResponse {
+        return_amount: quote_asset_amount,
+        toll_fee,
+        spread_fee
+    })
+}
+
+fn query_simulate_out_exact_quote(
+    deps: Deps,
+    quote_asset_amount: Uint256,
+    direction: Direction,
+) -> StdResult<SimulationResponse> {
+    let base_asset_amount = simulate_swapout_exact_quote(deps, quote_asset_amount, &direction)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let (toll_fee, spread_fee) = compute_fees(deps, quote_asset_amount)?;
+
+    Ok(SimulationResponse {
+        return_amount: base_asset_amount,
+        toll_fee,
+        spread_fee
+    })
+}
+
+// Everything a trader needs to know about a trade before making it, the same for opening and closing
+fn query_quote(
+    deps: Deps,
+    side: Direction,
+    amount: Uint256,
+    amount_kind: AmountKind,
+) -> StdResult<QuoteResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let curve = market_curve(&config.curve_type);
+    let state: State = STATE.load(deps.storage)?;
+
+    let (base_amount, quote_amount) = match amount_kind {
+        AmountKind::QUOTE => (simulate_swapin(deps, amount, &side), Ok(amount)),
+        AmountKind::BASE => (Ok(amount), simulate_swapin_exact_base(deps, amount, &side)),
+    };
+    let base_amount = base_amount.map_err(|err| StdError::generic_err(err.to_string()))?;
+    let quote_amount = quote_amount.map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    // Amounts are within the reserves, simulations return InsufficientReserve otherwise
+    let (new_quote_reserve, new_base_reserve) = match side {
+        Direction::LONG => {
+            let quote_reserve_amount = curve.quote_to_reserve(quote_amount, false)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            (state.quote_asset_reserve + quote_reserve_amount, state.base_asset_reserve - base_amount)
+        }
+        _ => {
+            let quote_reserve_amount = curve.quote_to_reserve(quote_amount, true)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            (state.quote_asset_reserve - quote_reserve_amount, state.base_asset_reserve + base_amount)
+        }
+    };
+
+    let mark_price_before = curve.mark_price(state.quote_asset_reserve, state.base_asset_reserve)?;
+    let mark_price_after = curve.mark_price(new_quote_reserve, new_base_reserve)?;
+    let average_price = if base_amount.is_zero() {
+        Decimal256::zero()
+    } else {
+        Decimal256::from_ratio(quote_amount, base_amount)
+    };
+    let (toll_fee, spread_fee) = compute_fees(deps, quote_amount)?;
+
+    Ok(QuoteResponse {
+        base_amount,
+        quote_amount,
+        average_price,
+        mark_price_before,
+        mark_price_after,
+        price_impact: price_change(mark_price_before, mark_price_after),
+        toll_fee,
+        spread_fee,
+        funding_rate: state.funding_rate
+    })
+}
+
+// Opening a long takes base out of the pool like closing a short, opening a short puts it in like closing a long
+pub fn simulate_swapin_exact_base(
+    deps: Deps,
+    base_asset_amount: Uint256,
+    direction: &Direction,
+) -> Result<Uint256, ContractError> {
+    let reverse = match direction {
+        Direction::LONG => Direction::SHORT,
+        Direction::SHORT => Direction::LONG,
+        Direction::NOT_SET => return Err(StdError::generic_err("Invalid Direction").into()),
+    };
+    simulate_swapout(deps, base_asset_amount, &reverse)
+}
+
+// Closing a long takes quote out of the pool like opening a short, closing a short puts it in like opening a long
+pub fn simulate_swapout_exact_quote(
+    deps: Deps,
+    quote_asset_amount: Uint256,
+    direction: &Direction,
+) -> Result<Uint256, ContractError> {
+    let reverse = match direction {
+        Direction::LONG => Direction::SHORT,
+        Direction::SHORT => Direction::LONG,
+        Direction::NOT_SET => return Err(StdError::generic_err("Invalid Direction").into()),
+    };
+    simulate_swapin(deps, quote_asset_amount, &reverse)
+}
+
 pub fn simulate_swapin(
     deps: Deps,
     quoteAmount: Uint256,
     direction: &Direction,
-) -> StdResult<Uint256> {
+) -> Result<Uint256, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
     let state = STATE.load(deps.storage)?;
     return simulate_swapin_internal(
+        market_curve(&config.curve_type).as_ref(),
         quoteAmount,
         direction,
         state.quote_asset_reserve,
@@ -337,27 +1165,34 @@ pub fn simulate_swapin(
     );
 }
 
-fn simulate_swapin_internal(
+pub fn simulate_swapin_internal(
+    curve: &dyn Curve,
     quoteAmount: Uint256,
     direction: &Direction,
     quote_reserve_amounts: Uint256,
     base_reserve_amounts: Uint256,
-) -> StdResult<Uint256> {
-    let k: Uint256 = quote_reserve_amounts * base_reserve_amounts; // x*y = k
-    let mut new_quote_reserve = match direction {
-        Direction::LONG => quote_reserve_amounts + quoteAmount,
-        Direction::SHORT => quote_reserve_amounts - quoteAmount,
+) -> Result<Uint256, ContractError> {
+    let new_quote_reserve = match direction {
+        Direction::LONG => checked_add(quote_reserve_amounts, curve.quote_to_reserve(quoteAmount, false)?)?,
+        Direction::SHORT => {
+            // Shorts cannot take out all the quote in the market
+            let quote_reserve_amount = curve.quote_to_reserve(quoteAmount, true)?;
+            if quote_reserve_amount >= quote_reserve_amounts {
+                return Err(ContractError::InsufficientReserve {});
+            }
+            quote_reserve_amounts - quote_reserve_amount
+        }
         Direction::NOT_SET => {
             return Err(StdError::generic_err("Invalid Direction").into());
         }
     };
 
-    let new_base_reserve: Uint256 = k / Decimal256::from_uint256(new_quote_reserve);
+    let new_base_reserve: Uint256 = curve.base_reserve_for(quote_reserve_amounts, base_reserve_amounts, new_quote_reserve)?;
 
-    let base_reserve_delta = if new_base_reserve > base_reserve_amounts {
-        new_base_reserve - base_reserve_amounts
-    } else {
-        base_reserve_amounts - new_base_reserve
+    // Rounding up can leave a tiny trade with nothing to receive
+    let base_reserve_delta = match direction {
+        Direction::LONG => saturating_sub(base_reserve_amounts, new_base_reserve),
+        _ => saturating_sub(new_base_reserve, base_reserve_amounts),
     };
 
     Ok(base_reserve_delta)
@@ -367,9 +1202,11 @@ pub fn simulate_swapout(
     deps: Deps,
     baseAmount: Uint256,
     direction: &Direction,
-) -> StdResult<Uint256> {
+) -> Result<Uint256, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
     let state = STATE.load(deps.storage)?;
     return simulate_swapout_internal(
+        market_curve(&config.curve_type).as_ref(),
         baseAmount,
         direction,
         state.quote_asset_reserve,
@@ -377,85 +1214,129 @@ pub fn simulate_swapout(
     );
 }
 
-fn simulate_swapout_internal(
+pub fn simulate_swapout_internal(
+    curve: &dyn Curve,
     baseAmount: Uint256,
     direction: &Direction,
     quote_reserve_amounts: Uint256,
     base_reserve_amounts: Uint256,
-) -> StdResult<Uint256> {
-    let k: Uint256 = quote_reserve_amounts * base_reserve_amounts; // x*y = k
-    let mut new_base_reserve = match direction {
+) -> Result<Uint256, ContractError> {
+    let new_base_reserve = match direction {
         Direction::LONG => {
-            base_reserve_amounts + baseAmount // Longs will close position by trading in base assets, and getting back quote assets
+            checked_add(base_reserve_amounts, baseAmount)? // Longs will close position by trading in base assets, and getting back quote assets
         }
         Direction::SHORT => {
-            base_reserve_amounts - baseAmount // Shorts will close position by trading in quote assets, and getting back base assets
+            // Shorts will close position by trading in quote assets, and getting back base assets
+            if baseAmount >= base_reserve_amounts {
+                return Err(ContractError::InsufficientReserve {});
+            }
+            base_reserve_amounts - baseAmount
         }
         Direction::NOT_SET => {
             return Err(StdError::generic_err("Invalid Direction").into());
         }
     };
 
-    let new_quote_reserve: Uint256 = k / Decimal256::from_uint256(new_base_reserve);
+    let new_quote_reserve: Uint256 = curve.quote_reserve_for(quote_reserve_amounts, base_reserve_amounts, new_base_reserve)?;
 
-    let quote_reserve_delta = if new_quote_reserve > quote_reserve_amounts {
-        new_quote_reserve - quote_reserve_amounts
-    } else {
-        quote_reserve_amounts - new_quote_reserve
+    let quote_reserve_delta = match direction {
+        Direction::LONG => saturating_sub(quote_reserve_amounts, new_quote_reserve),
+        _ => saturating_sub(new_quote_reserve, quote_reserve_amounts),
     };
 
-    Ok(quote_reserve_delta)
+    // Shorts pay quote in to buy back base
+    curve.reserve_to_quote(quote_reserve_delta, *direction == Direction::SHORT)
 }
 
 /*
-    ORACLE FUNCTIONS
+    QUERY FUNCTIONS
 */
 
-// Get the price of underlying asset
-pub fn get_underlying_price(deps: Deps) -> StdResult<Decimal256> {
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        contract_addr: deps.api.addr_humanize(&config.contract_addr)?,
+        owner_addr: deps.api.addr_humanize(&config.owner_addr)?,
+        bank_addr: deps.api.addr_humanize(&config.bank_addr)?,
+        oracle: oracle_config(deps, &config.oracle_type)?,
+        stable_denom: config.stable_denom,
+        base_denom: config.base_denom,
+        curve: curve_config(&config.curve_type),
+    })
+}
+
+// Refuse new exposure beyond the per-side open interest caps
+fn assert_open_interest(deps: Deps, new_state: &State) -> Result<(), ContractError> {
     let config: Config = CONFIG.load(deps.storage)?;
 
-    match config.oracle_type {
-        OracleType::NATIVE => {
-            return query_native_rate(&deps.querier, config.base_denom, config.stable_denom);
-        } // MIRROR, BAND
+    if !config.max_long_open_interest.is_zero() && new_state.total_long_size > config.max_long_open_interest {
+        return Err(ContractError::MaxOpenInterest {});
+    }
+    if !config.max_short_open_interest.is_zero() && new_state.total_short_size > config.max_short_open_interest {
+        return Err(ContractError::MaxOpenInterest {});
     }
-}
 
-// NATIVE ORACLE
-fn query_native_rate(
-    querier: &QuerierWrapper,
-    base_denom: String,
-    quote_denom: String,
-) -> StdResult<Decimal256> {
-    let terra_querier = TerraQuerier::new(querier);
-    let res: ExchangeRatesResponse =
-        terra_querier.query_exchange_rates(base_denom, vec![quote_denom])?;
+    Ok(())
+}
 
-    Ok(Decimal256::from(res.exchange_rates[0].exchange_rate))
+// Closing never takes a side below zero
+fn reduce_open_interest(total: Uint256, amount: Uint256) -> Uint256 {
+    saturating_sub(total, amount)
 }
 
-/*
-    QUERY FUNCTIONS
-*/
+fn query_market_price(deps: Deps, env: Env) -> StdResult<MarketPriceResponse> {
+    let state = STATE.load(deps.storage)?;
 
-fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
-    let config = CONFIG.load(deps.storage)?;
-    Ok(ConfigResponse {
-        contract_addr: deps.api.addr_humanize(&config.contract_addr)?,
-        bank_addr: deps.api.addr_humanize(&config.bank_addr)?,
-        stable_denom: config.stable_denom,
+    let mark_price = get_market_price(deps)?;
+    let index_price = query_oracle_price(deps, &env)?.price;
+
+    // Relative to the index, positive when the market trades above it
+    let spread = (SignedDecimal256::from(mark_price) - SignedDecimal256::from(index_price)) / index_price;
+
+    Ok(MarketPriceResponse {
+        mark_price,
+        twap_mark_price: get_twap_market_price(deps, &env, state.funding_period * Uint256::from(1_000_000u128))?,
+        index_price,
+        spread
     })
 }
 
-fn query_state(deps: Deps) -> StdResult<StateResponse> {
+fn query_market_info(deps: Deps) -> StdResult<MarketInfoResponse> {
+    let config = CONFIG.load(deps.storage)?;
     let state = STATE.load(deps.storage)?;
+
+    Ok(MarketInfoResponse {
+        base_symbol: config.base_denom.clone(),
+        stable_denom: config.stable_denom.clone(),
+        oracle_source: oracle_source(&config.oracle_type),
+        funding_period: (u128::from(state.funding_period) / 1000) as u64,
+        toll_ratio: config.toll_ratio,
+        spread_ratio: config.spread_ratio,
+        init_base_reserve: config.init_base_reserve,
+        init_quote_reserve: config.init_quote_reserve
+    })
+}
+
+fn query_state(deps: Deps, env: Env) -> StdResult<StateResponse> {
+    let mut state = STATE.load(deps.storage)?;
+    accrue_funding(deps, &env, &mut state)?;
+
     Ok(StateResponse {
         quote_asset_reserve: state.quote_asset_reserve,
         base_asset_reserve: state.base_asset_reserve,
         funding_premium_cumulative: state.aggregated_funding,
         funding_fee: state.funding_rate,
         market_price: get_market_price(deps)?,
-        underlying_price: get_underlying_price(deps)?
+        // The oracle may be gone once the market is shut down
+        underlying_price: match state.settlement_price {
+            Some(price) => price,
+            None => get_underlying_price(deps, &env)
+                .map_err(|err| StdError::generic_err(err.to_string()))?,
+        },
+        total_long_size: state.total_long_size,
+        total_short_size: state.total_short_size,
+        net_position_size: state.net_position_size,
+        settlement_price: state.settlement_price,
+        next_funding_time: ((u128::from(state.last_funding_time) + u128::from(state.funding_period) * 1_000_000) / 1_000_000_000) as u64
     })
 }
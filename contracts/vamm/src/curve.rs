@@ -0,0 +1,284 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{StdError, StdResult, Uint128};
+use seesaw::vamm::CurveConfig;
+
+use crate::error::ContractError;
+use crate::math::{checked_add, checked_div, checked_div_ceil, checked_mul, checked_sub};
+use crate::state::CurveType;
+
+// Same bound as Curve's pools, beyond it the curve is flat until a reserve is almost empty
+const MAX_AMPLIFICATION: u64 = 1_000_000;
+
+// Newton iterations before giving up on the StableSwap invariant, it usually converges within a few
+const MAX_ITERATIONS: usize = 255;
+
+/*
+    CURVE
+*/
+
+// Prices the swaps of a market. Reserves are stored in curve units, quote amounts traders pay and
+// receive are converted with quote_to_reserve and reserve_to_quote, which only a pegged curve does not leave as is.
+// Every rounding goes the market's way, a swap can only grow the invariant.
+pub trait Curve {
+    // Never decreases through a swap
+    fn invariant(&self, quote_reserve: Uint256, base_reserve: Uint256) -> Result<Uint256, ContractError>;
+
+    // Base reserve keeping the invariant once the quote reserve moves to new_quote_reserve, rounded up
+    fn base_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_quote_reserve: Uint256) -> Result<Uint256, ContractError>;
+
+    // Quote reserve keeping the invariant once the base reserve moves to new_base_reserve, rounded up
+    fn quote_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_base_reserve: Uint256) -> Result<Uint256, ContractError>;
+
+    // Quote asset paid for a base asset at the margin
+    fn mark_price(&self, quote_reserve: Uint256, base_reserve: Uint256) -> StdResult<Decimal256>;
+
+    // Quote reserve change for a quote amount, rounded up when the amount leaves the market
+    fn quote_to_reserve(&self, quote_amount: Uint256, _leaving: bool) -> Result<Uint256, ContractError> {
+        Ok(quote_amount)
+    }
+
+    // Quote amount for a quote reserve change, rounded up when the amount enters the market
+    fn reserve_to_quote(&self, reserve_amount: Uint256, _entering: bool) -> Result<Uint256, ContractError> {
+        Ok(reserve_amount)
+    }
+}
+
+pub fn market_curve(curve_type: &CurveType) -> Box<dyn Curve> {
+    match curve_type {
+        CurveType::CONSTANT_PRODUCT => Box::new(ConstantProduct {}),
+        CurveType::STABLE_SWAP { amplification, price_scale } => Box::new(StableSwap {
+            amplification: *amplification,
+            price_scale: *price_scale,
+        }),
+        CurveType::PEGGED { peg_multiplier } => Box::new(PeggedConstantProduct { peg_multiplier: *peg_multiplier }),
+    }
+}
+
+// Check the curve parameters of a new market, and convert them for storage
+pub fn validate_curve_config(
+    curve: &Option<CurveConfig>,
+    init_quote_reserve: Uint128,
+    init_base_reserve: Uint128,
+) -> StdResult<CurveType> {
+    match curve {
+        None | Some(CurveConfig::CONSTANT_PRODUCT {}) => Ok(CurveType::CONSTANT_PRODUCT),
+        Some(CurveConfig::STABLE_SWAP { amplification }) => {
+            if *amplification == 0 || *amplification > MAX_AMPLIFICATION {
+                return Err(StdError::generic_err(format!("Amplification must be between 1 and {}", MAX_AMPLIFICATION)));
+            }
+            if init_quote_reserve.is_zero() || init_base_reserve.is_zero() {
+                return Err(StdError::generic_err("StableSwap markets need both initial reserves"));
+            }
+
+            Ok(CurveType::STABLE_SWAP {
+                amplification: *amplification,
+                price_scale: Decimal256::from_ratio(Uint256::from(init_quote_reserve), Uint256::from(init_base_reserve)),
+            })
+        }
+        Some(CurveConfig::PEGGED { peg_multiplier }) => {
+            if peg_multiplier.is_zero() {
+                return Err(StdError::generic_err("Peg multiplier must be positive"));
+            }
+
+            Ok(CurveType::PEGGED { peg_multiplier: *peg_multiplier })
+        }
+    }
+}
+
+pub fn curve_config(curve_type: &CurveType) -> CurveConfig {
+    match curve_type {
+        CurveType::CONSTANT_PRODUCT => CurveConfig::CONSTANT_PRODUCT {},
+        CurveType::STABLE_SWAP { amplification, .. } => CurveConfig::STABLE_SWAP { amplification: *amplification },
+        CurveType::PEGGED { peg_multiplier } => CurveConfig::PEGGED { peg_multiplier: *peg_multiplier },
+    }
+}
+
+/*
+    CONSTANT PRODUCT
+*/
+
+pub struct ConstantProduct {}
+
+impl Curve for ConstantProduct {
+    fn invariant(&self, quote_reserve: Uint256, base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        checked_mul(quote_reserve, base_reserve) // x*y = k
+    }
+
+    fn base_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_quote_reserve: Uint256) -> Result<Uint256, ContractError> {
+        checked_div_ceil(self.invariant(quote_reserve, base_reserve)?, new_quote_reserve)
+    }
+
+    fn quote_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        checked_div_ceil(self.invariant(quote_reserve, base_reserve)?, new_base_reserve)
+    }
+
+    fn mark_price(&self, quote_reserve: Uint256, base_reserve: Uint256) -> StdResult<Decimal256> {
+        Ok(Decimal256::from_ratio(quote_reserve, base_reserve))
+    }
+}
+
+/*
+    PEGGED CONSTANT PRODUCT
+*/
+
+// x*y = k where a unit of the quote reserve is worth peg_multiplier quote asset.
+// The peg moves the mark price without touching the depth of the reserves.
+pub struct PeggedConstantProduct {
+    pub peg_multiplier: Decimal256,
+}
+
+impl Curve for PeggedConstantProduct {
+    fn invariant(&self, quote_reserve: Uint256, base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        ConstantProduct {}.invariant(quote_reserve, base_reserve)
+    }
+
+    fn base_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_quote_reserve: Uint256) -> Result<Uint256, ContractError> {
+        ConstantProduct {}.base_reserve_for(quote_reserve, base_reserve, new_quote_reserve)
+    }
+
+    fn quote_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        ConstantProduct {}.quote_reserve_for(quote_reserve, base_reserve, new_base_reserve)
+    }
+
+    fn mark_price(&self, quote_reserve: Uint256, base_reserve: Uint256) -> StdResult<Decimal256> {
+        Ok(Decimal256::from_ratio(quote_reserve, base_reserve) * self.peg_multiplier)
+    }
+
+    fn quote_to_reserve(&self, quote_amount: Uint256, leaving: bool) -> Result<Uint256, ContractError> {
+        let scaled = checked_mul(quote_amount, Uint256::from(Decimal256::DECIMAL_FRACTIONAL))?;
+        if leaving {
+            checked_div_ceil(scaled, Uint256::from(self.peg_multiplier.0))
+        } else {
+            checked_div(scaled, Uint256::from(self.peg_multiplier.0))
+        }
+    }
+
+    fn reserve_to_quote(&self, reserve_amount: Uint256, entering: bool) -> Result<Uint256, ContractError> {
+        let scaled = checked_mul(reserve_amount, Uint256::from(self.peg_multiplier.0))?;
+        if entering {
+            checked_div_ceil(scaled, Uint256::from(Decimal256::DECIMAL_FRACTIONAL))
+        } else {
+            checked_div(scaled, Uint256::from(Decimal256::DECIMAL_FRACTIONAL))
+        }
+    }
+}
+
+/*
+    STABLESWAP
+*/
+
+// Curve's StableSwap invariant for two assets, A*4*(x+y) + D = A*4*D + D^3/(4*x*y).
+// The base reserve is multiplied by price_scale, so the pool is balanced at the launch price.
+pub struct StableSwap {
+    pub amplification: u64,
+    pub price_scale: Decimal256,
+}
+
+impl StableSwap {
+    fn ann(&self) -> Uint256 {
+        Uint256::from(self.amplification as u128 * 4)
+    }
+
+    // Base reserve in quote terms, rounded down
+    fn normalize(&self, base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        checked_div(checked_mul(base_reserve, Uint256::from(self.price_scale.0))?, Uint256::from(Decimal256::DECIMAL_FRACTIONAL))
+    }
+
+    // Base reserve from quote terms, rounded up
+    fn denormalize(&self, normalized: Uint256) -> Result<Uint256, ContractError> {
+        checked_div_ceil(checked_mul(normalized, Uint256::from(Decimal256::DECIMAL_FRACTIONAL))?, Uint256::from(self.price_scale.0))
+    }
+
+    fn compute_d(&self, x: Uint256, y: Uint256) -> Result<Uint256, ContractError> {
+        let sum = checked_add(x, y)?;
+        if sum.is_zero() {
+            return Ok(Uint256::zero());
+        }
+
+        let ann = self.ann();
+        let mut d = sum;
+        for _ in 0..MAX_ITERATIONS {
+            // D^3 / (4*x*y)
+            let d_xy = checked_div(checked_mul(d, d)?, checked_mul(x, Uint256::from(2u128))?)?;
+            let d_xy = checked_div(checked_mul(d_xy, d)?, checked_mul(y, Uint256::from(2u128))?)?;
+
+            let previous = d;
+            let numerator = checked_mul(checked_add(checked_mul(ann, sum)?, checked_mul(d_xy, Uint256::from(2u128))?)?, d)?;
+            let denominator = checked_add(checked_mul(ann - Uint256::one(), d)?, checked_mul(d_xy, Uint256::from(3u128))?)?;
+            d = checked_div(numerator, denominator)?;
+
+            if converged(d, previous) {
+                return Ok(d);
+            }
+        }
+
+        Err(ContractError::Std(StdError::generic_err("StableSwap invariant did not converge")))
+    }
+
+    // Other reserve keeping the invariant d once one reserve is at x, rounded up
+    fn compute_y(&self, x: Uint256, d: Uint256) -> Result<Uint256, ContractError> {
+        let ann = self.ann();
+
+        // y^2 + (x + D/Ann - D)*y = D^3 / (4*x*Ann)
+        let c = checked_div(checked_mul(d, d)?, checked_mul(x, Uint256::from(2u128))?)?;
+        let c = checked_div(checked_mul(c, d)?, checked_mul(ann, Uint256::from(2u128))?)?;
+        let b = checked_add(x, checked_div(d, ann)?)?;
+
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let previous = y;
+            let numerator = checked_add(checked_mul(y, y)?, c)?;
+            let denominator = checked_sub(checked_add(checked_mul(y, Uint256::from(2u128))?, b)?, d)
+                .map_err(|_| ContractError::InsufficientReserve {})?;
+            y = checked_div(numerator, denominator)?;
+
+            if converged(y, previous) {
+                // Newton and the floored terms can each be one short
+                return checked_add(y, Uint256::from(2u128));
+            }
+        }
+
+        Err(ContractError::Std(StdError::generic_err("StableSwap reserve did not converge")))
+    }
+}
+
+fn converged(value: Uint256, previous: Uint256) -> bool {
+    if value > previous {
+        value - previous <= Uint256::one()
+    } else {
+        previous - value <= Uint256::one()
+    }
+}
+
+impl Curve for StableSwap {
+    fn invariant(&self, quote_reserve: Uint256, base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        self.compute_d(quote_reserve, self.normalize(base_reserve)?)
+    }
+
+    fn base_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_quote_reserve: Uint256) -> Result<Uint256, ContractError> {
+        let d = self.invariant(quote_reserve, base_reserve)?;
+        self.denormalize(self.compute_y(new_quote_reserve, d)?)
+    }
+
+    fn quote_reserve_for(&self, quote_reserve: Uint256, base_reserve: Uint256, new_base_reserve: Uint256) -> Result<Uint256, ContractError> {
+        let d = self.invariant(quote_reserve, base_reserve)?;
+        self.compute_y(self.normalize(new_base_reserve)?, d)
+    }
+
+    // Ratio of the partial derivatives of the invariant, scaled back to base units
+    fn mark_price(&self, quote_reserve: Uint256, base_reserve: Uint256) -> StdResult<Decimal256> {
+        let y = self.normalize(base_reserve).map_err(|err| StdError::generic_err(err.to_string()))?;
+        let d = self.compute_d(quote_reserve, y).map_err(|err| StdError::generic_err(err.to_string()))?;
+
+        let ann = Decimal256::from_uint256(self.ann());
+        let four = Decimal256::from_uint256(4u128);
+        let d_x = Decimal256::from_ratio(d, quote_reserve);
+        let d_y = Decimal256::from_ratio(d, y);
+
+        // Ann + D^3/(4*x*y^2) over Ann + D^3/(4*x^2*y)
+        let numerator = ann + d_x * d_y * d_y / four;
+        let denominator = ann + d_x * d_x * d_y / four;
+
+        Ok(numerator / denominator * self.price_scale)
+    }
+}
@@ -19,4 +19,37 @@ pub enum ContractError {
     
     #[error("Position still open")]
     PositionAlreadyOpen {},
+
+    #[error("Operation exceeds max spread limit")]
+    MaxSlippage {},
+
+    #[error("Swap moves the mark price more than allowed")]
+    MaxPriceImpact {},
+
+    #[error("Trade exceeds the open interest cap")]
+    MaxOpenInterest {},
+
+    #[error("Trade exceeds the reserves of the market")]
+    InsufficientReserve {},
+
+    #[error("Arithmetic overflow")]
+    Overflow {},
+
+    #[error("Swap would decrease k")]
+    InvariantViolation {},
+
+    #[error("Not supported by the curve of the market")]
+    UnsupportedCurve {},
+
+    #[error("Market is shut down")]
+    MarketClosed {},
+
+    #[error("Cost exceeds the remaining budget")]
+    BudgetExceeded {},
+
+    #[error("Oracle price is older than the allowed age")]
+    StaleOracle {},
+
+    #[error("Oracle price moved more than the allowed deviation")]
+    OracleDeviation {},
 }
@@ -1,6 +1,10 @@
 pub mod contract;
 pub mod error;
 pub mod state;
+pub mod oracle;
+pub mod admin;
+pub mod math;
+pub mod curve;
 pub mod response;
 
 #[cfg(test)]
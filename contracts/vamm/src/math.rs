@@ -0,0 +1,64 @@
+use cosmwasm_bignumber::Uint256;
+
+use crate::error::ContractError;
+
+// Uint256 operators panic or wrap on overflow, the AMM math goes through these instead
+
+pub fn checked_add(a: Uint256, b: Uint256) -> Result<Uint256, ContractError> {
+    let (sum, overflow) = a.0.overflowing_add(b.0);
+    if overflow {
+        return Err(ContractError::Overflow {});
+    }
+    Ok(Uint256::from(sum))
+}
+
+pub fn checked_sub(a: Uint256, b: Uint256) -> Result<Uint256, ContractError> {
+    if b > a {
+        return Err(ContractError::Overflow {});
+    }
+    Ok(a - b)
+}
+
+pub fn saturating_sub(a: Uint256, b: Uint256) -> Uint256 {
+    if a > b {
+        a - b
+    } else {
+        Uint256::zero()
+    }
+}
+
+pub fn checked_mul(a: Uint256, b: Uint256) -> Result<Uint256, ContractError> {
+    let (product, overflow) = a.0.overflowing_mul(b.0);
+    if overflow {
+        return Err(ContractError::Overflow {});
+    }
+    Ok(Uint256::from(product))
+}
+
+pub fn checked_div(a: Uint256, b: Uint256) -> Result<Uint256, ContractError> {
+    if b.is_zero() {
+        return Err(ContractError::InsufficientReserve {});
+    }
+    Ok(Uint256::from(a.0 / b.0))
+}
+
+// Rounds up, so reserves derived from k never let k decrease
+pub fn checked_div_ceil(a: Uint256, b: Uint256) -> Result<Uint256, ContractError> {
+    if b.is_zero() {
+        return Err(ContractError::InsufficientReserve {});
+    }
+
+    let quotient = Uint256::from(a.0 / b.0);
+    if (a.0 % b.0).is_zero() {
+        Ok(quotient)
+    } else {
+        checked_add(quotient, Uint256::one())
+    }
+}
+
+pub fn to_i64(amount: Uint256) -> Result<i64, ContractError> {
+    if amount > Uint256::from(i64::MAX as u64) {
+        return Err(ContractError::Overflow {});
+    }
+    Ok(amount.0.low_u64() as i64)
+}
@@ -0,0 +1,274 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Addr, Decimal, Deps, Env, QuerierWrapper, QueryRequest, StdError, StdResult, Uint128, WasmQuery, to_binary};
+use seesaw::vamm::{OracleConfig, OraclePriceResponse, OracleSource, OracleStatus, OracleStatusResponse};
+use terra_cosmwasm::{ExchangeRatesResponse, TerraQuerier};
+
+use crate::error::ContractError;
+use crate::state::{CONFIG, Config, OracleType, STATE, State};
+
+/*
+    MIRROR ORACLE MESSAGES
+*/
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorOracleQueryMsg {
+    Price { base_asset: String, quote_asset: String }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MirrorPriceResponse {
+    pub rate: Decimal,
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64
+}
+
+/*
+    BAND ORACLE MESSAGES
+*/
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BandOracleQueryMsg {
+    GetReferenceData { base_symbol: String, quote_symbol: String }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BandReferenceData {
+    pub rate: Uint128, // Rate of base/quote, multiplied by 1e18
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64
+}
+
+// Band rates are fixed point numbers with 18 decimals
+const BAND_RATE_MULTIPLIER: u128 = 1_000_000_000_000_000_000;
+
+/*
+    ORACLE FUNCTIONS
+*/
+
+// Price read from an oracle, with the time (in seconds) it was last updated
+#[derive(Clone, Debug, PartialEq)]
+pub struct OraclePrice {
+    pub price: Decimal256,
+    pub last_updated: u64
+}
+
+// Get the price of underlying asset, refusing to return a price that fails the oracle guards
+pub fn get_underlying_price(deps: Deps, env: &Env) -> Result<Decimal256, ContractError> {
+    let oracle_price = query_oracle_price(deps, env)?;
+
+    match check_oracle_price(deps, env, &oracle_price)? {
+        OracleStatus::Valid => Ok(oracle_price.price),
+        OracleStatus::Stale => Err(ContractError::StaleOracle {}),
+        OracleStatus::Deviation => Err(ContractError::OracleDeviation {}),
+    }
+}
+
+// Read the price of underlying asset from the configured oracle, without any guards
+pub fn query_oracle_price(deps: Deps, env: &Env) -> StdResult<OraclePrice> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    match config.oracle_type {
+        OracleType::NATIVE => {
+            query_native_rate(&deps.querier, env, config.base_denom, config.stable_denom)
+        }
+        OracleType::MIRROR { oracle_addr, asset_token } => {
+            query_mirror_rate(
+                &deps.querier,
+                deps.api.addr_humanize(&oracle_addr)?,
+                deps.api.addr_humanize(&asset_token)?,
+                config.stable_denom
+            )
+        }
+        OracleType::BAND { oracle_addr, base_symbol, quote_symbol } => {
+            query_band_rate(
+                &deps.querier,
+                deps.api.addr_humanize(&oracle_addr)?,
+                base_symbol,
+                quote_symbol
+            )
+        }
+    }
+}
+
+// Check the age of an oracle price, and how far it moved since the previous read that passed the guards
+pub fn check_oracle_price(deps: Deps, env: &Env, oracle_price: &OraclePrice) -> StdResult<OracleStatus> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state: State = STATE.load(deps.storage)?;
+
+    let age = env.block.time.seconds().saturating_sub(oracle_price.last_updated);
+    if config.max_oracle_price_age != 0 && age > config.max_oracle_price_age {
+        return Ok(OracleStatus::Stale);
+    }
+
+    if !config.max_oracle_price_deviation.is_zero() && !state.last_oracle_price.is_zero() {
+        let price_change = if oracle_price.price > state.last_oracle_price {
+            oracle_price.price - state.last_oracle_price
+        } else {
+            state.last_oracle_price - oracle_price.price
+        };
+
+        if price_change / state.last_oracle_price > config.max_oracle_price_deviation {
+            return Ok(OracleStatus::Deviation);
+        }
+    }
+
+    Ok(OracleStatus::Valid)
+}
+
+pub fn query_oracle_status(deps: Deps, env: &Env) -> StdResult<OracleStatusResponse> {
+    let oracle_price = query_oracle_price(deps, env)?;
+
+    Ok(OracleStatusResponse {
+        status: check_oracle_price(deps, env, &oracle_price)?,
+        price: oracle_price.price,
+        last_updated: oracle_price.last_updated
+    })
+}
+
+pub fn query_underlying_price(deps: Deps, env: &Env) -> StdResult<OraclePriceResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let oracle_price = query_oracle_price(deps, env)?;
+
+    Ok(OraclePriceResponse {
+        price: oracle_price.price,
+        source: oracle_source(&config.oracle_type),
+        last_updated: oracle_price.last_updated
+    })
+}
+
+pub fn oracle_source(oracle_type: &OracleType) -> OracleSource {
+    match oracle_type {
+        OracleType::NATIVE => OracleSource::NATIVE,
+        OracleType::MIRROR { .. } => OracleSource::MIRROR,
+        OracleType::BAND { .. } => OracleSource::BAND,
+    }
+}
+
+// Check the oracle parameters of a new market, and convert them for storage
+pub fn validate_oracle_config(
+    deps: Deps,
+    oracle: &OracleConfig,
+    base_denom: &str,
+    stable_denom: &str,
+    max_oracle_price_age: u64,
+) -> StdResult<OracleType> {
+    if base_denom.is_empty() || stable_denom.is_empty() {
+        return Err(StdError::generic_err("Base and stable denoms must be set"));
+    }
+    if base_denom == stable_denom {
+        return Err(StdError::generic_err("Base asset must differ from the stable denom"));
+    }
+
+    match oracle {
+        OracleConfig::NATIVE {} => {
+            // Treasury rates carry no update time, a max age could never trip
+            if max_oracle_price_age != 0 {
+                return Err(StdError::generic_err("Native oracle prices have no age, max oracle price age must be 0"));
+            }
+
+            Ok(OracleType::NATIVE)
+        }
+        OracleConfig::MIRROR { oracle_addr, asset_token } => Ok(OracleType::MIRROR {
+            oracle_addr: deps.api.addr_canonicalize(deps.api.addr_validate(oracle_addr)?.as_str())?,
+            asset_token: deps.api.addr_canonicalize(deps.api.addr_validate(asset_token)?.as_str())?,
+        }),
+        OracleConfig::BAND { oracle_addr, base_symbol, quote_symbol } => {
+            if base_symbol.is_empty() || quote_symbol.is_empty() || base_symbol == quote_symbol {
+                return Err(StdError::generic_err("Band oracle needs distinct base and quote symbols"));
+            }
+
+            Ok(OracleType::BAND {
+                oracle_addr: deps.api.addr_canonicalize(deps.api.addr_validate(oracle_addr)?.as_str())?,
+                base_symbol: base_symbol.clone(),
+                quote_symbol: quote_symbol.clone(),
+            })
+        }
+    }
+}
+
+pub fn oracle_config(deps: Deps, oracle_type: &OracleType) -> StdResult<OracleConfig> {
+    match oracle_type {
+        OracleType::NATIVE => Ok(OracleConfig::NATIVE {}),
+        OracleType::MIRROR { oracle_addr, asset_token } => Ok(OracleConfig::MIRROR {
+            oracle_addr: deps.api.addr_humanize(oracle_addr)?.to_string(),
+            asset_token: deps.api.addr_humanize(asset_token)?.to_string(),
+        }),
+        OracleType::BAND { oracle_addr, base_symbol, quote_symbol } => Ok(OracleConfig::BAND {
+            oracle_addr: deps.api.addr_humanize(oracle_addr)?.to_string(),
+            base_symbol: base_symbol.clone(),
+            quote_symbol: quote_symbol.clone(),
+        }),
+    }
+}
+
+// NATIVE ORACLE
+fn query_native_rate(
+    querier: &QuerierWrapper,
+    env: &Env,
+    base_denom: String,
+    quote_denom: String,
+) -> StdResult<OraclePrice> {
+    let terra_querier = TerraQuerier::new(querier);
+    let res: ExchangeRatesResponse =
+        terra_querier.query_exchange_rates(base_denom.clone(), vec![quote_denom.clone()])?;
+
+    let rate = res.exchange_rates
+        .iter()
+        .find(|item| item.quote_denom == quote_denom)
+        .ok_or_else(|| StdError::generic_err(format!("No exchange rate for {}/{}", base_denom, quote_denom)))?;
+
+    // The treasury does not report when a rate was voted in, so native prices are never stale.
+    // Staleness is not checked for them, instantiate rejects a max age on a native oracle.
+    Ok(OraclePrice {
+        price: Decimal256::from(rate.exchange_rate),
+        last_updated: env.block.time.seconds()
+    })
+}
+
+// MIRROR ORACLE
+fn query_mirror_rate(
+    querier: &QuerierWrapper,
+    oracle_addr: Addr,
+    asset_token: Addr,
+    quote_denom: String,
+) -> StdResult<OraclePrice> {
+    let res: MirrorPriceResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_addr.to_string(),
+        msg: to_binary(&MirrorOracleQueryMsg::Price {
+            base_asset: asset_token.to_string(),
+            quote_asset: quote_denom,
+        })?,
+    }))?;
+
+    Ok(OraclePrice {
+        price: Decimal256::from(res.rate),
+        last_updated: res.last_updated_base.min(res.last_updated_quote)
+    })
+}
+
+// BAND ORACLE
+fn query_band_rate(
+    querier: &QuerierWrapper,
+    oracle_addr: Addr,
+    base_symbol: String,
+    quote_symbol: String,
+) -> StdResult<OraclePrice> {
+    let res: BandReferenceData = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_addr.to_string(),
+        msg: to_binary(&BandOracleQueryMsg::GetReferenceData {
+            base_symbol,
+            quote_symbol,
+        })?,
+    }))?;
+
+    Ok(OraclePrice {
+        price: Decimal256::from_ratio(Uint256::from(res.rate), Uint256::from(BAND_RATE_MULTIPLIER)),
+        last_updated: res.last_updated_base.min(res.last_updated_quote)
+    })
+}
@@ -1,27 +1,50 @@
-use std::collections::VecDeque;
-
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use terraswap::asset::{AssetInfoRaw};
 use cosmwasm_std::{Api, CanonicalAddr, Decimal, Order, StdResult, Storage};
-use cw_storage_plus::{Item,Map};
+use cw_storage_plus::{Item, Map, U64Key};
 
-use seesaw::{bank::{Direction}, vamm::Funding};
+use seesaw::{bank::{Direction}, number::SignedDecimal256, vamm::{Funding, WhoPays}};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub enum OracleType {
-    NATIVE // Right now only native oracle implemented, will add Band and Mirror oracles in future.
+    NATIVE, // Terra treasury exchange rates of base_denom
+    MIRROR { oracle_addr: CanonicalAddr, asset_token: CanonicalAddr }, // Mirror oracle, for mAsset markets
+    BAND { oracle_addr: CanonicalAddr, base_symbol: String, quote_symbol: String } // Band standard reference contract
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum CurveType {
+    CONSTANT_PRODUCT,
+    STABLE_SWAP { amplification: u64, price_scale: Decimal256 }, // Base reserve is scaled by the launch price, the curve is flattest there
+    PEGGED { peg_multiplier: Decimal256 }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Config {
     pub contract_addr: CanonicalAddr,
+    pub owner_addr: CanonicalAddr,
     pub bank_addr: CanonicalAddr,
     pub stable_denom: String, // i.e. Quote denom
     pub oracle_type: OracleType,
-    pub base_denom: String, // Optional, required if OracleType = Native
+    pub base_denom: String, // Base asset, a native denom for NATIVE oracles and a symbol otherwise
+    pub max_oracle_price_age: u64, // In seconds, 0 disables the check, always 0 for NATIVE
+    pub max_oracle_price_deviation: Decimal256, // Max change between oracle reads, 0 disables the check
+    pub snapshot_retention: u64, // In seconds, snapshots older than this are pruned
+    pub toll_ratio: Decimal256, // Fee on notional traded, goes to the fee recipient
+    pub spread_ratio: Decimal256, // Fee on notional traded, goes to the fee recipient
+    pub max_trade_price_impact: Decimal256, // Max mark price change of a single swap, 0 disables the check
+    pub max_block_price_impact: Decimal256, // Max mark price change within a block, 0 disables the check
+    pub repeg_budget: Uint256, // Max total cost the system takes on from repegs, in quote asset
+    pub update_k_budget: Uint256, // Max total cost the system takes on from changing k, in quote asset
+    pub max_funding_rate: Decimal256, // Max funding rate per funding period, 0 disables the cap
+    pub init_base_reserve: Uint256, // Reserves at launch
+    pub init_quote_reserve: Uint256,
+    pub max_long_open_interest: Uint256, // In base asset, 0 disables the cap
+    pub max_short_open_interest: Uint256, // In base asset, 0 disables the cap
+    pub curve_type: CurveType,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -30,15 +53,24 @@ pub struct State {
     pub quote_asset_reserve: Uint256,
     pub base_asset_reserve: Uint256,
     pub funding_period: Uint256,
-    pub aggregated_funding: Decimal256,
+    pub aggregated_funding: SignedDecimal256, // Cumulative premium per base asset, longs pay when it increases
     pub funding_rate: Funding,
-    pub last_funding_time: Uint256
+    pub last_funding_time: Uint256,
+    pub last_accrual_time: Uint256, // Time aggregated_funding was accrued up to, in nanoseconds
+    pub last_oracle_price: Decimal256, // Last oracle price that passed the guards, the deviation guard measures from it
+    pub net_position_size: i64, // Base assets held by traders, positive if net long
+    pub total_long_size: Uint256, // Base assets held by longs
+    pub total_short_size: Uint256, // Base assets owed by shorts
+    pub repeg_cost_spent: Uint256, // Total cost of past repegs, counts against repeg_budget
+    pub update_k_cost_spent: Uint256, // Total cost of past k changes, counts against update_k_budget
+    pub settlement_price: Option<Decimal256> // Set when the market is shut down, positions settle at it
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub struct MarketSnapshots {
-    pub snapshots: Vec<SnapshotItem>
+pub struct SnapshotIndex {
+    pub first: u64, // Oldest snapshot still stored
+    pub next: u64 // Index the next snapshot is stored at
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -48,10 +80,44 @@ pub struct SnapshotItem {
     pub quote_asset_reserve: Uint256,
     pub base_delta: i64,
     pub timestamp: u64,
+    pub cumulative_price: Decimal256, // Mark price times the nanoseconds it held, summed from launch up to this snapshot
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CandleItem {
+    pub open: Decimal256, // Mark price before the first swap of the bucket
+    pub high: Decimal256,
+    pub low: Decimal256,
+    pub close: Decimal256,
+    pub base_volume: Uint256,
+    pub quote_volume: Uint256
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FundingSettlementItem {
+    pub timestamp: u64, // Block time of the settlement, in nanoseconds
+    pub premium_fraction: Decimal256,
+    pub funding_rate: Decimal256,
+    pub mark_price: Decimal256, // TWAP of the mark price over the period
+    pub index_price: Decimal256,
+    pub who_pays: WhoPays
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
 pub const STATE: Item<State> = Item::new("state");
 
-pub const SNAPSHOTS: Item<MarketSnapshots> = Item::new("snapshot");
\ No newline at end of file
+pub const SNAPSHOTS: Map<U64Key, SnapshotItem> = Map::new("snapshots");
+
+pub const SNAPSHOT_INDEX: Item<SnapshotIndex> = Item::new("snapshot_index");
+
+// Index of the last snapshot taken at each timestamp, in nanoseconds
+pub const SNAPSHOT_TIMES: Map<U64Key, u64> = Map::new("snapshot_times");
+
+// Keyed by resolution and bucket start, both in seconds
+pub const CANDLES: Map<(U64Key, U64Key), CandleItem> = Map::new("candles");
+
+// Keyed by funding period number, the end of the period divided by the period length
+pub const FUNDING_HISTORY: Map<U64Key, FundingSettlementItem> = Map::new("funding_history");
\ No newline at end of file
@@ -0,0 +1,240 @@
+use std::str::FromStr;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{StdResult, Uint128, from_binary};
+use seesaw::bank::Sign;
+use seesaw::bank::Direction;
+use seesaw::vamm::{ExecuteMsg, InstantiateMsg, OracleConfig, QueryMsg, RepegResponse, SettlementSource, StateResponse, UpdateKResponse};
+
+use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
+use crate::state::{STATE, State};
+use crate::testing::mock_querier::mock_dependencies;
+
+fn repeg_msg(repeg_budget: Uint256) -> InstantiateMsg {
+    // Mark price 900, oracle price 1000
+    InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(900_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget,
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    }
+}
+
+#[test]
+fn repeg_net_long() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, repeg_msg(Uint256::from(10_000u128))).unwrap();
+
+    STATE.update(deps.as_mut().storage, |state| -> StdResult<State> {
+        Ok(State { net_position_size: 100, ..state })
+    }).unwrap();
+
+    // Longs gain from the higher price, so the system pays 100_000 * 100 / 1_100
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateRepeg {}).unwrap();
+    let repeg: RepegResponse = from_binary(&res).unwrap();
+    assert_eq!(repeg.quote_asset_reserve, Uint256::from(1_000_000u128));
+    assert_eq!(repeg.mark_price_before, Decimal256::from_uint256(Uint256::from(900u128)));
+    assert_eq!(repeg.mark_price_after, Decimal256::from_uint256(Uint256::from(1_000u128)));
+    assert_eq!(repeg.cost, Uint256::from(9_090u128));
+    assert_eq!(repeg.cost_sign, Sign::Positive);
+
+    let info = mock_info("anyone", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Repeg {});
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let info = mock_info("owner", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Repeg {}).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.quote_asset_reserve, Uint256::from(1_000_000u128));
+    assert_eq!(state.repeg_cost_spent, Uint256::from(9_090u128));
+}
+
+#[test]
+fn repeg_budget() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), repeg_msg(Uint256::from(5_000u128))).unwrap();
+
+    STATE.update(deps.as_mut().storage, |state| -> StdResult<State> {
+        Ok(State { net_position_size: 100, ..state })
+    }).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::Repeg {});
+    match res.unwrap_err() {
+        ContractError::BudgetExceeded {} => {}
+        _ => panic!("Must return budget exceeded error"),
+    }
+
+    // Shorts lose from the higher price, so the repeg is free
+    STATE.update(deps.as_mut().storage, |state| -> StdResult<State> {
+        Ok(State { net_position_size: -100, ..state })
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateRepeg {}).unwrap();
+    let repeg: RepegResponse = from_binary(&res).unwrap();
+    assert_eq!(repeg.cost, Uint256::from(11_111u128));
+    assert_eq!(repeg.cost_sign, Sign::Negative);
+
+    let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Repeg {}).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.quote_asset_reserve, Uint256::from(1_000_000u128));
+    assert_eq!(state.repeg_cost_spent, Uint256::zero());
+}
+
+#[test]
+fn repeg_net_short_beyond_reserve() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), repeg_msg(Uint256::from(10_000u128))).unwrap();
+
+    // Net short as large as the base reserve, closing it would need more base than the market holds
+    STATE.update(deps.as_mut().storage, |state| -> StdResult<State> {
+        Ok(State { net_position_size: -(u128::from(state.base_asset_reserve) as i64), ..state })
+    }).unwrap();
+
+    query(deps.as_ref(), mock_env(), QueryMsg::SimulateRepeg {}).unwrap_err();
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Repeg {}).unwrap_err();
+}
+
+#[test]
+fn update_k() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = InstantiateMsg {
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        update_k_budget: Uint256::from(5_000u128),
+        ..repeg_msg(Uint256::zero())
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    STATE.update(deps.as_mut().storage, |state| -> StdResult<State> {
+        Ok(State { net_position_size: 100, ..state })
+    }).unwrap();
+
+    // Longs close 100 base for 95_238 instead of 90_909 on the deeper market
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateUpdateK { scale: Decimal256::from_str("2").unwrap() }).unwrap();
+    let update: UpdateKResponse = from_binary(&res).unwrap();
+    assert_eq!(update.base_asset_reserve, Uint256::from(2_000u128));
+    assert_eq!(update.quote_asset_reserve, Uint256::from(2_000_000u128));
+    assert_eq!(update.mark_price, Decimal256::from_uint256(Uint256::from(1_000u128)));
+    assert_eq!(update.cost, Uint256::from(4_329u128));
+    assert_eq!(update.cost_sign, Sign::Positive);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::UpdateK { scale: Decimal256::from_str("2").unwrap() });
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let _res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::UpdateK { scale: Decimal256::from_str("2").unwrap() }).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.base_asset_reserve, Uint256::from(2_000u128));
+    assert_eq!(state.quote_asset_reserve, Uint256::from(2_000_000u128));
+    assert_eq!(state.update_k_cost_spent, Uint256::from(4_329u128));
+
+    // A second doubling would go over the budget
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::UpdateK { scale: Decimal256::from_str("2").unwrap() });
+    match res.unwrap_err() {
+        ContractError::BudgetExceeded {} => {}
+        _ => panic!("Must return budget exceeded error"),
+    }
+
+    // Making the market shallower is paid by the traders
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateUpdateK { scale: Decimal256::percent(50) }).unwrap();
+    let update: UpdateKResponse = from_binary(&res).unwrap();
+    assert_eq!(update.cost_sign, Sign::Negative);
+
+    let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::UpdateK { scale: Decimal256::percent(50) }).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.base_asset_reserve, Uint256::from(1_000u128));
+    assert_eq!(state.update_k_cost_spent, Uint256::from(4_329u128));
+}
+
+#[test]
+fn shutdown() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), repeg_msg(Uint256::zero())).unwrap();
+
+    let msg = ExecuteMsg::Shutdown { price_source: SettlementSource::ORACLE };
+    match execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.settlement_price, Some(Decimal256::from_uint256(1_000u128)));
+
+    // Trading, funding and admin changes are all frozen
+    let bank = mock_info("bank0000", &[]);
+    let msgs = vec![
+        ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000u128), base_amount_limit: None, belief_price: None, max_spread: None },
+        ExecuteMsg::SwapOut { direction: Direction::LONG, base_asset_amount: Uint256::from(1u128), quote_amount_limit: None, belief_price: None, max_spread: None },
+    ];
+    for msg in msgs {
+        match execute(deps.as_mut(), mock_env(), bank.clone(), msg).unwrap_err() {
+            ContractError::MarketClosed {} => {}
+            _ => panic!("Must return market closed error"),
+        }
+    }
+
+    match execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::Repeg {}).unwrap_err() {
+        ContractError::MarketClosed {} => {}
+        _ => panic!("Must return market closed error"),
+    }
+    match execute(deps.as_mut(), mock_env(), info, msg).unwrap_err() {
+        ContractError::MarketClosed {} => {}
+        _ => panic!("Must return market closed error"),
+    }
+}
+
+#[test]
+fn shutdown_at_twap() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), repeg_msg(Uint256::zero())).unwrap();
+
+    // Without trades the mark price held at 900 over the whole period
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3_600);
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Shutdown { price_source: SettlementSource::TWAP }).unwrap();
+
+    let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.settlement_price, Some(Decimal256::from_uint256(900u128)));
+}
@@ -0,0 +1,211 @@
+use std::str::FromStr;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Uint128, from_binary};
+use seesaw::bank::Direction;
+use seesaw::vamm::{ConfigResponse, CurveConfig, ExecuteMsg, InstantiateMsg, OracleConfig, QueryMsg, SimulationResponse, StateResponse};
+
+use crate::contract::{execute, instantiate, query, simulate_swapin_internal, simulate_swapout_internal};
+use crate::curve::{ConstantProduct, Curve, PeggedConstantProduct, StableSwap};
+use crate::error::ContractError;
+use crate::testing::mock_querier::mock_dependencies;
+
+// Every curve at a few market sizes, StableSwap balanced at the price of the reserves
+fn curves(quote_reserve: Uint256, base_reserve: Uint256) -> Vec<Box<dyn Curve>> {
+    vec![
+        Box::new(ConstantProduct {}),
+        Box::new(StableSwap { amplification: 100, price_scale: Decimal256::from_ratio(quote_reserve, base_reserve) }),
+        Box::new(PeggedConstantProduct { peg_multiplier: Decimal256::from_str("1.5").unwrap() }),
+    ]
+}
+
+fn markets() -> Vec<(Uint256, Uint256)> {
+    vec![
+        (Uint256::from(1_000_000u128), Uint256::from(1_000u128)), // 1000 per base
+        (Uint256::from(1_000_000_000u128), Uint256::from(1_000_000_000u128)), // 1 per base
+        (Uint256::from(1_000_000_000_000u128), Uint256::from(1_180_000_000_000_000u128)), // KRW/UST
+    ]
+}
+
+// Deterministic spread of trade sizes between 1 and half of the quote reserve
+fn trade_sizes(quote_reserve: Uint256) -> Vec<Uint256> {
+    let half = u128::from(quote_reserve) / 2;
+    let mut seed: u128 = 0x2545F4914F6CDD1D;
+    let mut sizes = vec![Uint256::one(), Uint256::from(half)];
+    for _ in 0..30 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407) % (1 << 64);
+        sizes.push(Uint256::from(1 + seed % half));
+    }
+    sizes
+}
+
+#[test]
+fn curve_long_round_trip() {
+    for (quote_reserve, base_reserve) in markets() {
+        for curve in curves(quote_reserve, base_reserve) {
+            let k = curve.invariant(quote_reserve, base_reserve).unwrap();
+            let mark_price = curve.mark_price(quote_reserve, base_reserve).unwrap();
+
+            for quote_amount in trade_sizes(quote_reserve) {
+                let base_amount = simulate_swapin_internal(curve.as_ref(), quote_amount, &Direction::LONG, quote_reserve, base_reserve).unwrap();
+                assert!(base_amount < base_reserve);
+
+                let new_quote_reserve = quote_reserve + curve.quote_to_reserve(quote_amount, false).unwrap();
+                let new_base_reserve = base_reserve - base_amount;
+
+                // Buying base grows the invariant and the price
+                assert!(curve.invariant(new_quote_reserve, new_base_reserve).unwrap() >= k);
+                assert!(curve.mark_price(new_quote_reserve, new_base_reserve).unwrap() >= mark_price);
+
+                // Selling it straight back never returns more than was paid
+                let quote_back = simulate_swapout_internal(curve.as_ref(), base_amount, &Direction::LONG, new_quote_reserve, new_base_reserve).unwrap();
+                assert!(quote_back <= quote_amount);
+            }
+        }
+    }
+}
+
+#[test]
+fn curve_short_round_trip() {
+    for (quote_reserve, base_reserve) in markets() {
+        for curve in curves(quote_reserve, base_reserve) {
+            let k = curve.invariant(quote_reserve, base_reserve).unwrap();
+            let mark_price = curve.mark_price(quote_reserve, base_reserve).unwrap();
+
+            for quote_amount in trade_sizes(quote_reserve) {
+                let base_amount = simulate_swapin_internal(curve.as_ref(), quote_amount, &Direction::SHORT, quote_reserve, base_reserve).unwrap();
+
+                let new_quote_reserve = quote_reserve - curve.quote_to_reserve(quote_amount, true).unwrap();
+                let new_base_reserve = base_reserve + base_amount;
+
+                // Selling base grows the invariant and lowers the price
+                assert!(curve.invariant(new_quote_reserve, new_base_reserve).unwrap() >= k);
+                assert!(curve.mark_price(new_quote_reserve, new_base_reserve).unwrap() <= mark_price);
+
+                // Buying it straight back never costs less than was received
+                let quote_paid = simulate_swapout_internal(curve.as_ref(), base_amount, &Direction::SHORT, new_quote_reserve, new_base_reserve).unwrap();
+                assert!(quote_paid >= quote_amount);
+            }
+        }
+    }
+}
+
+#[test]
+fn curve_reserve_bounds() {
+    for (quote_reserve, base_reserve) in markets() {
+        for curve in curves(quote_reserve, base_reserve) {
+            // Shorts cannot take out all the quote, nor can anyone buy back all the base
+            let all_quote = curve.reserve_to_quote(quote_reserve, true).unwrap();
+            match simulate_swapin_internal(curve.as_ref(), all_quote, &Direction::SHORT, quote_reserve, base_reserve).unwrap_err() {
+                ContractError::InsufficientReserve {} => {}
+                _ => panic!("Must return insufficient reserve error"),
+            }
+            match simulate_swapout_internal(curve.as_ref(), base_reserve, &Direction::SHORT, quote_reserve, base_reserve).unwrap_err() {
+                ContractError::InsufficientReserve {} => {}
+                _ => panic!("Must return insufficient reserve error"),
+            }
+        }
+    }
+}
+
+fn curve_msg(curve: Option<CurveConfig>) -> InstantiateMsg {
+    InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve,
+        owner: None
+    }
+}
+
+fn long_base_amount(curve: Option<CurveConfig>) -> Uint256 {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, curve_msg(curve)).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateIn { quoteAmount: Uint256::from(100_000u128), direction: Direction::LONG }).unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+    simulation.return_amount
+}
+
+#[test]
+fn curve_selection() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = curve_msg(None);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.curve, CurveConfig::CONSTANT_PRODUCT {});
+
+    // Invalid parameters
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), curve_msg(Some(CurveConfig::STABLE_SWAP { amplification: 0 }))).unwrap_err();
+    instantiate(deps.as_mut(), mock_env(), info, curve_msg(Some(CurveConfig::PEGGED { peg_multiplier: Decimal256::zero() }))).unwrap_err();
+
+    // 100k quote buys 90.9 base at x*y=k, StableSwap is flatter around the launch price
+    let constant_product = long_base_amount(None);
+    assert_eq!(constant_product, Uint256::from(90u128));
+    assert_eq!(long_base_amount(Some(CurveConfig::STABLE_SWAP { amplification: 100 })), Uint256::from(99u128));
+
+    // A peg of 2 doubles the price, 100k quote is 50k of the quote reserve
+    assert_eq!(long_base_amount(Some(CurveConfig::PEGGED { peg_multiplier: Decimal256::from_uint256(2u128) })), Uint256::from(47u128));
+}
+
+#[test]
+fn pegged_market() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let msg = curve_msg(Some(CurveConfig::PEGGED { peg_multiplier: Decimal256::from_uint256(2u128) }));
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.market_price, Decimal256::from_uint256(2000u128));
+
+    // Trades pay real quote, the reserve moves by half of it
+    let info = mock_info("bank0000", &[]);
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(100_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.quote_asset_reserve, Uint256::from(1_050_000u128));
+    assert_eq!(state.base_asset_reserve, Uint256::from(953u128));
+
+    // Closing returns no more than was paid
+    let msg = ExecuteMsg::SwapOut { direction: Direction::LONG, base_asset_amount: Uint256::from(47u128), quote_amount_limit: Some(Uint256::from(98_700u128)), belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert!(state.quote_asset_reserve >= Uint256::from(1_000_000u128));
+
+    // Repeg costs assume x*y=k in quote asset
+    let info = mock_info("owner", &[]);
+    match execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Repeg {}).unwrap_err() {
+        ContractError::UnsupportedCurve {} => {}
+        _ => panic!("Must return unsupported curve error"),
+    }
+}
+
@@ -0,0 +1,90 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::Uint128;
+use seesaw::bank::Direction;
+use seesaw::vamm::{ExecuteMsg, InstantiateMsg, OracleConfig};
+
+use crate::contract::{execute, instantiate};
+use crate::error::ContractError;
+use crate::math::{checked_add, checked_div_ceil, checked_mul, checked_sub, to_i64};
+use crate::testing::mock_querier::mock_dependencies;
+
+#[test]
+fn checked_math() {
+    let max = Uint256::from(Decimal256::MAX.0);
+
+    match checked_add(max, Uint256::one()).unwrap_err() {
+        ContractError::Overflow {} => {}
+        _ => panic!("Must return overflow error"),
+    }
+    match checked_mul(max, Uint256::from(2u128)).unwrap_err() {
+        ContractError::Overflow {} => {}
+        _ => panic!("Must return overflow error"),
+    }
+    match checked_sub(Uint256::one(), Uint256::from(2u128)).unwrap_err() {
+        ContractError::Overflow {} => {}
+        _ => panic!("Must return overflow error"),
+    }
+    match to_i64(Uint256::from(i64::MAX as u128 + 1)).unwrap_err() {
+        ContractError::Overflow {} => {}
+        _ => panic!("Must return overflow error"),
+    }
+
+    assert_eq!(to_i64(Uint256::from(i64::MAX as u128)).unwrap(), i64::MAX);
+    assert_eq!(checked_div_ceil(Uint256::from(10u128), Uint256::from(5u128)).unwrap(), Uint256::from(2u128));
+    assert_eq!(checked_div_ceil(Uint256::from(11u128), Uint256::from(5u128)).unwrap(), Uint256::from(3u128));
+}
+
+#[test]
+fn swaps_beyond_reserves() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("bank0000", &[]);
+
+    // Shorts cannot take out all the quote in the market
+    let msg = ExecuteMsg::SwapIn { direction: Direction::SHORT, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::InsufficientReserve {} => {}
+        _ => panic!("Must return insufficient reserve error"),
+    }
+
+    // Nor buy back more base than the market holds
+    let msg = ExecuteMsg::SwapOut { direction: Direction::SHORT, base_asset_amount: Uint256::from(1_001u128), quote_amount_limit: None, belief_price: None, max_spread: None };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::InsufficientReserve {} => {}
+        _ => panic!("Must return insufficient reserve error"),
+    }
+
+    let msg = ExecuteMsg::SwapInExactBase { direction: Direction::LONG, base_asset_amount: Uint256::from(1_000u128), max_quote: None };
+    match execute(deps.as_mut(), mock_env(), info, msg).unwrap_err() {
+        ContractError::InsufficientReserve {} => {}
+        _ => panic!("Must return insufficient reserve error"),
+    }
+}
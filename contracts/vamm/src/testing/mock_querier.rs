@@ -8,6 +8,8 @@ use std::collections::HashMap;
 use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
 use terra_cosmwasm::{ExchangeRateItem, ExchangeRatesResponse, TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
 
+use crate::oracle::{BandOracleQueryMsg, BandReferenceData, MirrorOracleQueryMsg, MirrorPriceResponse};
+
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
 /// this uses our CustomQuerier.
 pub fn mock_dependencies(
@@ -27,6 +29,8 @@ pub struct WasmMockQuerier {
     base: MockQuerier<TerraQueryWrapper>,
     token_querier: TokenQuerier,
     tax_querier: TaxQuerier,
+    mirror_oracle_querier: MirrorOracleQuerier,
+    band_oracle_querier: BandOracleQuerier,
 }
 
 #[derive(Clone, Default)]
@@ -82,6 +86,50 @@ pub(crate) fn caps_to_map(caps: &[(&String, &Uint128)]) -> HashMap<String, Uint1
     owner_map
 }
 
+#[derive(Clone, Default)]
+pub struct MirrorOracleQuerier {
+    // this lets us look up the price of an asset token, with its last update time
+    prices: HashMap<String, (Decimal, u64)>,
+}
+
+impl MirrorOracleQuerier {
+    pub fn new(prices: &[(&String, &Decimal, &u64)]) -> Self {
+        MirrorOracleQuerier {
+            prices: mirror_prices_to_map(prices),
+        }
+    }
+}
+
+pub(crate) fn mirror_prices_to_map(prices: &[(&String, &Decimal, &u64)]) -> HashMap<String, (Decimal, u64)> {
+    let mut price_map: HashMap<String, (Decimal, u64)> = HashMap::new();
+    for (asset_token, rate, last_updated) in prices.iter() {
+        price_map.insert(asset_token.to_string(), (**rate, **last_updated));
+    }
+    price_map
+}
+
+#[derive(Clone, Default)]
+pub struct BandOracleQuerier {
+    // this lets us look up the 1e18 scaled rate of a (base, quote) symbol pair
+    rates: HashMap<(String, String), Uint128>,
+}
+
+impl BandOracleQuerier {
+    pub fn new(rates: &[(&str, &str, &Uint128)]) -> Self {
+        BandOracleQuerier {
+            rates: band_rates_to_map(rates),
+        }
+    }
+}
+
+pub(crate) fn band_rates_to_map(rates: &[(&str, &str, &Uint128)]) -> HashMap<(String, String), Uint128> {
+    let mut rate_map: HashMap<(String, String), Uint128> = HashMap::new();
+    for (base_symbol, quote_symbol, rate) in rates.iter() {
+        rate_map.insert((base_symbol.to_string(), quote_symbol.to_string()), **rate);
+    }
+    rate_map
+}
+
 impl Querier for WasmMockQuerier {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
         // MockQuerier doesn't support Custom, so we ignore it completely here
@@ -155,6 +203,40 @@ impl WasmMockQuerier {
                 }
             }
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Ok(MirrorOracleQueryMsg::Price { base_asset, quote_asset: _ }) = from_binary(msg) {
+                    return match self.mirror_oracle_querier.prices.get(&base_asset) {
+                        Some((rate, last_updated)) => SystemResult::Ok(ContractResult::Ok(
+                            to_binary(&MirrorPriceResponse {
+                                rate: *rate,
+                                last_updated_base: *last_updated,
+                                last_updated_quote: u64::MAX,
+                            })
+                            .unwrap(),
+                        )),
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: format!("No oracle price exists for the asset {}", base_asset),
+                            request: msg.as_slice().into(),
+                        }),
+                    };
+                }
+
+                if let Ok(BandOracleQueryMsg::GetReferenceData { base_symbol, quote_symbol }) = from_binary(msg) {
+                    return match self.band_oracle_querier.rates.get(&(base_symbol.clone(), quote_symbol.clone())) {
+                        Some(rate) => SystemResult::Ok(ContractResult::Ok(
+                            to_binary(&BandReferenceData {
+                                rate: *rate,
+                                last_updated_base: 1_571_797_419u64,
+                                last_updated_quote: 1_571_797_419u64,
+                            })
+                            .unwrap(),
+                        )),
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: format!("No reference data exists for {}/{}", base_symbol, quote_symbol),
+                            request: msg.as_slice().into(),
+                        }),
+                    };
+                }
+
                 match from_binary(&msg).unwrap() {
                     Cw20QueryMsg::TokenInfo {} => {
                         let balances: &HashMap<String, Uint128> =
@@ -232,6 +314,8 @@ impl WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
             tax_querier: TaxQuerier::default(),
+            mirror_oracle_querier: MirrorOracleQuerier::default(),
+            band_oracle_querier: BandOracleQuerier::default(),
         }
     }
 
@@ -245,6 +329,16 @@ impl WasmMockQuerier {
         self.tax_querier = TaxQuerier::new(rate, caps);
     }
 
+    // configure the mirror oracle mock querier
+    pub fn with_mirror_prices(&mut self, prices: &[(&String, &Decimal, &u64)]) {
+        self.mirror_oracle_querier = MirrorOracleQuerier::new(prices);
+    }
+
+    // configure the band oracle mock querier
+    pub fn with_band_rates(&mut self, rates: &[(&str, &str, &Uint128)]) {
+        self.band_oracle_querier = BandOracleQuerier::new(rates);
+    }
+
     pub fn with_balance(&mut self, balances: &[(&String, Vec<Coin>)]) {
         for (addr, balance) in balances {
             self.base.update_balance(addr.to_string(), balance.clone());
@@ -1,2 +1,7 @@
 mod mock_querier;
-mod trades;
\ No newline at end of file
+mod trades;
+mod oracle;
+mod snapshots;
+mod admin;
+mod math;
+mod curve;
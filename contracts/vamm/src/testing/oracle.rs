@@ -0,0 +1,572 @@
+use std::str::FromStr;
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Decimal, Uint128, from_binary};
+use seesaw::bank::Direction;
+use seesaw::number::SignedDecimal256;
+use seesaw::vamm::{ConfigResponse, ExecuteMsg, InstantiateMsg, OracleConfig, MarketInfoResponse, MarketPriceResponse, OraclePriceResponse, OracleSource, OracleStatus, OracleStatusResponse, QueryMsg, StateResponse};
+
+use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
+use crate::state::{STATE, State};
+use crate::testing::mock_querier::mock_dependencies;
+
+#[test]
+fn native_oracle_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.underlying_price, Decimal256::from_uint256(Uint256::from(1000u128)));
+}
+
+#[test]
+fn mirror_oracle_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_mirror_prices(&[
+        (&"mAAPL0000".to_string(), &Decimal::from_ratio(150u128, 1u128), &1_571_797_419u64)
+    ]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "mAAPL".to_string(),
+        oracle: OracleConfig::MIRROR {
+            oracle_addr: "oracle0000".to_string(),
+            asset_token: "mAAPL0000".to_string()
+        },
+        init_quote_reserve: Uint128::from(150_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.underlying_price, Decimal256::from_uint256(Uint256::from(150u128)));
+    assert_eq!(state.market_price, Decimal256::from_uint256(Uint256::from(150u128)));
+}
+
+#[test]
+fn mirror_oracle_missing_asset() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "mTSLA".to_string(),
+        oracle: OracleConfig::MIRROR {
+            oracle_addr: "oracle0000".to_string(),
+            asset_token: "mTSLA0000".to_string()
+        },
+        init_quote_reserve: Uint128::from(150_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap_err();
+}
+
+#[test]
+fn band_oracle_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_band_rates(&[
+        ("KRW", "USD", &Uint128::from(850_000_000_000_000u128)) // 0.00085 USD per KRW
+    ]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "KRW".to_string(),
+        oracle: OracleConfig::BAND {
+            oracle_addr: "band0000".to_string(),
+            base_symbol: "KRW".to_string(),
+            quote_symbol: "USD".to_string()
+        },
+        init_quote_reserve: Uint128::from(850u128),
+        init_base_reserve: Uint128::from(1_000_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.underlying_price, Decimal256::from_str("0.00085").unwrap());
+}
+
+#[test]
+fn stale_oracle_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    // Last updated 10 minutes before the mock block time
+    deps.querier.with_mirror_prices(&[
+        (&"mAAPL0000".to_string(), &Decimal::from_ratio(150u128, 1u128), &(1_571_797_419u64 - 600))
+    ]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "mAAPL".to_string(),
+        oracle: OracleConfig::MIRROR {
+            oracle_addr: "oracle0000".to_string(),
+            asset_token: "mAAPL0000".to_string()
+        },
+        init_quote_reserve: Uint128::from(150_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 60,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::OracleStatus { }).unwrap();
+    let oracle_status: OracleStatusResponse = from_binary(&res).unwrap();
+
+    assert_eq!(oracle_status.status, OracleStatus::Stale);
+    assert_eq!(oracle_status.last_updated, 1_571_797_419u64 - 600);
+
+    // The bank values positions from the state, it refuses a stale price
+    query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap_err();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(28_800);
+
+    let info = mock_info("bank0000", &[]);
+    let err = execute(deps.as_mut(), env, info, ExecuteMsg::SettleFunding { }).unwrap_err();
+
+    match err {
+        ContractError::StaleOracle {} => {}
+        _ => panic!("Must return stale oracle error"),
+    }
+}
+
+#[test]
+fn oracle_price_deviation() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::from_str("0.1").unwrap(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // First settlement has no previous oracle read to compare with
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(28_800);
+
+    let info = mock_info("bank0000", &[]);
+    execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::SettleFunding { }).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.last_oracle_price, Decimal256::from_uint256(Uint256::from(1000u128)));
+
+    // Oracle now reports 1000, twice the previous read
+    STATE.update(deps.as_mut().storage, |state| -> cosmwasm_std::StdResult<State> {
+        Ok(State { last_oracle_price: Decimal256::from_uint256(Uint256::from(500u128)), ..state })
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::OracleStatus { }).unwrap();
+    let oracle_status: OracleStatusResponse = from_binary(&res).unwrap();
+
+    assert_eq!(oracle_status.status, OracleStatus::Deviation);
+
+    env.block.time = env.block.time.plus_seconds(28_800);
+    let err = execute(deps.as_mut(), env, info.clone(), ExecuteMsg::SettleFunding { }).unwrap_err();
+
+    match err {
+        ContractError::OracleDeviation {} => {}
+        _ => panic!("Must return oracle deviation error"),
+    }
+
+    // The bank values positions from the state, it refuses a jumped price
+    query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap_err();
+
+    // Only the owner can accept the new price
+    let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::ResetOracle { }).unwrap_err();
+
+    match err {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let info = mock_info("owner", &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::ResetOracle { }).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.last_oracle_price, Decimal256::from_uint256(Uint256::from(1000u128)));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::OracleStatus { }).unwrap();
+    let oracle_status: OracleStatusResponse = from_binary(&res).unwrap();
+
+    assert_eq!(oracle_status.status, OracleStatus::Valid);
+}
+
+#[test]
+fn oracle_deviation_pauses_funding() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_100_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::from_str("0.1").unwrap(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Oracle moved from 500 to 1000 since the last read
+    STATE.update(deps.as_mut().storage, |state| -> cosmwasm_std::StdResult<State> {
+        Ok(State { last_oracle_price: Decimal256::from_uint256(Uint256::from(500u128)), ..state })
+    }).unwrap();
+
+    // Nothing accrues while the deviation stands, the reference does not follow on its own
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3_600);
+
+    let info = mock_info("owner", &[]);
+    execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::ResetOracle { }).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.aggregated_funding, SignedDecimal256::zero());
+    assert_eq!(state.last_accrual_time, Uint256::from(env.block.time.nanos()));
+
+    // Accrual resumes once the owner accepted the new price
+    env.block.time = env.block.time.plus_seconds(3_600);
+
+    let res = query(deps.as_ref(), env, QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.funding_premium_cumulative, SignedDecimal256::from(Decimal256::from_ratio(Uint256::from(100u128), Uint256::from(24u128))));
+}
+
+#[test]
+fn oracle_deviation_follows_reads() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_mirror_prices(&[
+        (&"mAAPL0000".to_string(), &Decimal::from_ratio(150u128, 1u128), &1_571_797_419u64)
+    ]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "mAAPL".to_string(),
+        oracle: OracleConfig::MIRROR {
+            oracle_addr: "oracle0000".to_string(),
+            asset_token: "mAAPL0000".to_string()
+        },
+        init_quote_reserve: Uint128::from(150_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::from_str("0.1").unwrap(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Every trade reads the oracle and moves the reference to it
+    let mut env = mock_env();
+    for price in [150u128, 160u128, 175u128].iter() {
+        env.block.time = env.block.time.plus_seconds(3_600);
+        deps.querier.with_mirror_prices(&[
+            (&"mAAPL0000".to_string(), &Decimal::from_ratio(*price, 1u128), &env.block.time.seconds())
+        ]);
+
+        // 175 is 16.7% above the first read, but only 9.4% above the previous one
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::OracleStatus { }).unwrap();
+        let oracle_status: OracleStatusResponse = from_binary(&res).unwrap();
+        assert_eq!(oracle_status.status, OracleStatus::Valid);
+
+        let info = mock_info("bank0000", &[]);
+        let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let state: State = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.last_oracle_price, Decimal256::from_uint256(Uint256::from(*price)));
+    }
+}
+
+#[test]
+fn price_queries() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_100_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::permille(1),
+        spread_ratio: Decimal256::permille(2),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::OraclePrice { }).unwrap();
+    let oracle_price: OraclePriceResponse = from_binary(&res).unwrap();
+
+    assert_eq!(oracle_price, OraclePriceResponse {
+        price: Decimal256::from_uint256(Uint256::from(1000u128)),
+        source: OracleSource::NATIVE,
+        last_updated: mock_env().block.time.seconds()
+    });
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketPrice { }).unwrap();
+    let market_price: MarketPriceResponse = from_binary(&res).unwrap();
+
+    assert_eq!(market_price, MarketPriceResponse {
+        mark_price: Decimal256::from_uint256(Uint256::from(1100u128)),
+        twap_mark_price: Decimal256::from_uint256(Uint256::from(1100u128)),
+        index_price: Decimal256::from_uint256(Uint256::from(1000u128)),
+        spread: SignedDecimal256::from(Decimal256::percent(10))
+    });
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketInfo { }).unwrap();
+    let market_info: MarketInfoResponse = from_binary(&res).unwrap();
+
+    assert_eq!(market_info, MarketInfoResponse {
+        base_symbol: "uluna".to_string(),
+        stable_denom: "uusd".to_string(),
+        oracle_source: OracleSource::NATIVE,
+        funding_period: 8 * 60 * 60,
+        toll_ratio: Decimal256::permille(1),
+        spread_ratio: Decimal256::permille(2),
+        init_base_reserve: Uint256::from(1_000u128),
+        init_quote_reserve: Uint256::from(1_100_000u128)
+    });
+}
+
+#[test]
+fn oracle_config_validation() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "KRW".to_string(),
+        oracle: OracleConfig::BAND {
+            oracle_addr: "band0000".to_string(),
+            base_symbol: "KRW".to_string(),
+            quote_symbol: "KRW".to_string()
+        },
+        init_quote_reserve: Uint128::from(850u128),
+        init_base_reserve: Uint128::from(1_000_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    instantiate(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+
+    // Base asset cannot be the stable denom
+    let invalid_msg = InstantiateMsg {
+        base_denom: "uusd".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        ..msg.clone()
+    };
+    instantiate(deps.as_mut(), mock_env(), info.clone(), invalid_msg).unwrap_err();
+
+    // Native prices have no age to check
+    let invalid_msg = InstantiateMsg {
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        max_oracle_price_age: 60,
+        ..msg.clone()
+    };
+    instantiate(deps.as_mut(), mock_env(), info.clone(), invalid_msg).unwrap_err();
+
+    let msg = InstantiateMsg {
+        oracle: OracleConfig::BAND {
+            oracle_addr: "band0000".to_string(),
+            base_symbol: "KRW".to_string(),
+            quote_symbol: "USD".to_string()
+        },
+        ..msg
+    };
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config { }).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+
+    assert_eq!(config.base_denom, "KRW".to_string());
+    assert_eq!(config.oracle, OracleConfig::BAND {
+        oracle_addr: "band0000".to_string(),
+        base_symbol: "KRW".to_string(),
+        quote_symbol: "USD".to_string()
+    });
+}
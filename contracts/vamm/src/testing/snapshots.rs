@@ -0,0 +1,255 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Env, Uint128, from_binary};
+use seesaw::bank::Direction;
+use seesaw::vamm::{CandlesResponse, ExecuteMsg, InstantiateMsg, OracleConfig, MarketSnapshotsResponse, QueryMsg};
+
+use crate::contract::{execute, instantiate, query};
+use crate::state::{SNAPSHOT_INDEX, SnapshotIndex};
+use crate::testing::mock_querier::mock_dependencies;
+
+fn env_at(seconds_after_launch: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(seconds_after_launch);
+    env
+}
+
+#[test]
+fn paginate_snapshots() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // One swap every minute
+    let info = mock_info("bank0000", &[]);
+    for minute in 1..=5u64 {
+        let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(10_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+        execute(deps.as_mut(), env_at(minute * 60), info.clone(), msg).unwrap();
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: None, limit: Some(2), start_time: None, end_time: None }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
+    assert_eq!(res.snapshots.iter().map(|s| s.index).collect::<Vec<u64>>(), vec![0, 1]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: Some(1), limit: Some(2), start_time: None, end_time: None }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
+    assert_eq!(res.snapshots.iter().map(|s| s.index).collect::<Vec<u64>>(), vec![2, 3]);
+
+    // Time range from minute 2 to minute 4, inclusive
+    let start_time = env_at(120).block.time.nanos();
+    let end_time = env_at(240).block.time.nanos();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: None, limit: None, start_time: Some(start_time), end_time: Some(end_time) }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
+    assert_eq!(res.snapshots.iter().map(|s| s.index).collect::<Vec<u64>>(), vec![2, 3, 4]);
+    assert_eq!(res.snapshots[0].timestamp, start_time);
+
+    // Start between two snapshots, paging on from within the range
+    let start_time = env_at(150).block.time.nanos();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: None, limit: None, start_time: Some(start_time), end_time: None }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
+    assert_eq!(res.snapshots.iter().map(|s| s.index).collect::<Vec<u64>>(), vec![3, 4, 5]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: Some(3), limit: None, start_time: Some(start_time), end_time: None }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
+    assert_eq!(res.snapshots.iter().map(|s| s.index).collect::<Vec<u64>>(), vec![4, 5]);
+}
+
+#[test]
+fn prune_snapshots() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 28_800,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // One swap every hour for a day
+    let info = mock_info("bank0000", &[]);
+    for hour in 1..=24u64 {
+        let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(10_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+        execute(deps.as_mut(), env_at(hour * 3600), info.clone(), msg).unwrap();
+    }
+
+    // Snapshot 16 was live 8 hours before the last swap, everything older is pruned
+    let index: SnapshotIndex = SNAPSHOT_INDEX.load(&deps.storage).unwrap();
+    assert_eq!(index, SnapshotIndex { first: 16, next: 25 });
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: None, limit: Some(30), start_time: None, end_time: None }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
+    assert_eq!(res.snapshots.len(), 9);
+    assert_eq!(res.snapshots[0].index, 16);
+
+    // TWAP of the last two hours from the prices set by the swaps of hours 22 and 23
+    let prices: Vec<Decimal256> = res.snapshots.iter()
+        .map(|snapshot| Decimal256::from_ratio(snapshot.quote_asset_reserve, snapshot.base_asset_reserve))
+        .collect();
+    let hour = Decimal256::from_uint256(3_600_000_000_000u128);
+    let expected = (prices[6] * hour + prices[7] * hour) / (hour + hour);
+
+    let res = query(deps.as_ref(), env_at(24 * 3600), QueryMsg::TwapMarkPrice { interval_seconds: 7_200 }).unwrap();
+    let twap: Decimal256 = from_binary(&res).unwrap();
+    assert_eq!(twap, expected);
+
+    // Reaching back past the pruned snapshots averages over what is kept, from the start of snapshot 16
+    let kept = Decimal256::from_uint256(8u128) * hour;
+    let expected = prices[..8].iter().fold(Decimal256::zero(), |sum, price| sum + *price * hour) / kept;
+
+    let res = query(deps.as_ref(), env_at(24 * 3600), QueryMsg::TwapMarkPrice { interval_seconds: 86_400 }).unwrap();
+    let twap: Decimal256 = from_binary(&res).unwrap();
+    assert_eq!(twap, expected);
+}
+
+#[test]
+fn snapshot_retention_covers_funding_period() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 3_600,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+}
+
+#[test]
+fn candles() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("bank0000", &[]);
+    let long = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(10_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    let short = ExecuteMsg::SwapIn { direction: Direction::SHORT, quote_asset_amount: Uint256::from(30_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+
+    // Launch is 39 seconds into a minute, so the third swap starts a new one minute candle
+    execute(deps.as_mut(), env_at(0), info.clone(), long.clone()).unwrap();
+    execute(deps.as_mut(), env_at(10), info.clone(), long).unwrap();
+    execute(deps.as_mut(), env_at(30), info, short).unwrap();
+
+    let launch = mock_env().block.time.seconds();
+    let res = query(deps.as_ref(), env_at(30), QueryMsg::Candles { resolution_seconds: 60, start: launch, end: launch + 3_600 }).unwrap();
+    let minutes: CandlesResponse = from_binary(&res).unwrap();
+
+    assert_eq!(minutes.candles.len(), 2);
+    assert_eq!(minutes.candles[0].start, launch - 39);
+    assert_eq!(minutes.candles[1].start, launch + 21);
+
+    // Two longs push the price up from the launch price
+    assert_eq!(minutes.candles[0].open, Decimal256::from_uint256(1_000u128));
+    assert_eq!(minutes.candles[0].low, minutes.candles[0].open);
+    assert_eq!(minutes.candles[0].high, minutes.candles[0].close);
+    assert!(minutes.candles[0].close > minutes.candles[0].open);
+    assert_eq!(minutes.candles[0].quote_volume, Uint256::from(20_000u128));
+
+    // The short opens where the previous candle closed and pushes the price down
+    assert_eq!(minutes.candles[1].open, minutes.candles[0].close);
+    assert_eq!(minutes.candles[1].high, minutes.candles[1].open);
+    assert_eq!(minutes.candles[1].low, minutes.candles[1].close);
+    assert_eq!(minutes.candles[1].quote_volume, Uint256::from(30_000u128));
+
+    // All swaps fall in the same hour
+    let res = query(deps.as_ref(), env_at(30), QueryMsg::Candles { resolution_seconds: 3_600, start: launch, end: launch + 3_600 }).unwrap();
+    let hourly: CandlesResponse = from_binary(&res).unwrap();
+
+    assert_eq!(hourly.candles.len(), 1);
+    assert_eq!(hourly.candles[0].open, Decimal256::from_uint256(1_000u128));
+    assert_eq!(hourly.candles[0].high, minutes.candles[0].high);
+    assert_eq!(hourly.candles[0].low, minutes.candles[1].low);
+    assert_eq!(hourly.candles[0].close, minutes.candles[1].close);
+    assert_eq!(hourly.candles[0].quote_volume, Uint256::from(50_000u128));
+    assert_eq!(hourly.candles[0].base_volume, minutes.candles[0].base_volume + minutes.candles[1].base_volume);
+
+    query(deps.as_ref(), env_at(30), QueryMsg::Candles { resolution_seconds: 120, start: launch, end: launch + 3_600 }).unwrap_err();
+}
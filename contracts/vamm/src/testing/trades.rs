@@ -1,14 +1,17 @@
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::{Addr, BankMsg, Coin, ContractResult, CosmosMsg, Decimal, Reply, Response, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg, from_binary, to_binary};
-use seesaw::vamm::{InstantiateMsg, ExecuteMsg, QueryMsg, StateResponse, Funding, WhoPays};
+use cosmwasm_std::{Addr, BankMsg, Coin, ContractResult, CosmosMsg, Decimal, Reply, Response, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg, attr, from_binary, to_binary};
+use seesaw::vamm::{AmountKind, InstantiateMsg, OracleConfig, ExecuteMsg, QueryMsg, StateResponse, Funding, FundingHistoryResponse, FundingSettlementResponse, WhoPays, MarketSnapshotsResponse, QuoteResponse, SimulationResponse};
 use seesaw::bank::{Direction };
+use seesaw::number::SignedDecimal256;
 
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
 use crate::contract::{instantiate, execute, query};
 use crate::error::ContractError;
-use crate::state::{MarketSnapshots, SnapshotItem};
+use crate::state::{STATE, State};
+use std::str::FromStr;
+
 use crate::testing::mock_querier::mock_dependencies;
 
 #[test]
@@ -27,18 +30,42 @@ fn swap_in() {
     let msg = InstantiateMsg {
         stable_denom: "uusd".to_string(),
         bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
         init_quote_reserve: Uint128::from(1_000_000u128),
-        init_base_reserve: Uint128::from(1_000u128)
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
     };
 
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let info = mock_info("depositor", &vec![Coin {
+    let info = mock_info("bank0000", &vec![Coin {
         denom: "uusd".to_string(),
         amount: Uint128::from(100u128)
     }]);
 
-    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1000u128) };
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+
+    // 1000 quote would buy 0.999 base, which rounds down to nothing in the market's favour
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::InvalidZeroAmount {} => {}
+        _ => panic!("Must return invalid zero amount error"),
+    }
+
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(2000u128), base_amount_limit: None, belief_price: None, max_spread: None };
 
     // check mint ib token
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -46,14 +73,18 @@ fn swap_in() {
     let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
     let state: StateResponse = from_binary(&res).unwrap();
 
-    assert_eq!(state.quote_asset_reserve, Uint256::from(1001000u128));
+    assert_eq!(state.quote_asset_reserve, Uint256::from(1002000u128));
     assert_eq!(state.base_asset_reserve, Uint256::from(999u128));
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { }).unwrap();
-    let snapshots: MarketSnapshots = from_binary(&res).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::MarketSnapshots { start_after: None, limit: None, start_time: None, end_time: None }).unwrap();
+    let res: MarketSnapshotsResponse = from_binary(&res).unwrap();
 
     // TEST SNAPSHOTS
-    assert_eq!(snapshots, snapshots);
+    assert_eq!(res.snapshots.len(), 2);
+    assert_eq!(res.snapshots[1].index, 1u64);
+    assert_eq!(res.snapshots[1].quote_asset_reserve, Uint256::from(1002000u128));
+    assert_eq!(res.snapshots[1].base_asset_reserve, Uint256::from(999u128));
+    assert_eq!(res.snapshots[1].base_delta, 1i64);
 }
 
 #[test]
@@ -72,26 +103,48 @@ fn swap_out() {
     let msg = InstantiateMsg {
         stable_denom: "uusd".to_string(),
         bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
         init_quote_reserve: Uint128::from(1_000_000u128),
-        init_base_reserve: Uint128::from(1_000u128)
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
     };
 
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    let info = mock_info("depositor", &vec![Coin {
+    let msg = ExecuteMsg::SwapOut { direction: Direction::LONG, base_asset_amount: Uint256::from(10u128), quote_amount_limit: None, belief_price: None, max_spread: None };
+
+    // Only the bank trades against the market, it holds the margin
+    match execute(deps.as_mut(), mock_env(), mock_info("depositor", &vec![]), msg.clone()).unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    let info = mock_info("bank0000", &vec![Coin {
         denom: "uusd".to_string(),
         amount: Uint128::from(100u128)
     }]);
 
-    let msg = ExecuteMsg::SwapOut { direction: Direction::LONG, base_asset_amount: Uint256::from(10u128) };
-
     // check mint ib token
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
     let state: StateResponse = from_binary(&res).unwrap();
 
-    assert_eq!(state.quote_asset_reserve, Uint256::from(990099u128));
+    assert_eq!(state.quote_asset_reserve, Uint256::from(990100u128));
     assert_eq!(state.base_asset_reserve, Uint256::from(1010u128));
 }
 
@@ -105,8 +158,24 @@ fn funding_rates_access_control() {
     let msg = InstantiateMsg {
         stable_denom: "uusd".to_string(),
         bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
         init_quote_reserve: Uint128::from(1_000_000u128),
-        init_base_reserve: Uint128::from(1_000u128)
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
     };
     
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -128,20 +197,40 @@ fn funding_rates() {
     let msg = InstantiateMsg {
         stable_denom: "uusd".to_string(),
         bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
         init_quote_reserve: Uint128::from(1_100_000u128),
-        init_base_reserve: Uint128::from(1_000u128)
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
     };
 
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
+    // Premium accrues over one funding period
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(8 * 60 * 60);
+
     let info = mock_info("bank0000", &vec![]);
     
     let msg = ExecuteMsg::SettleFunding { };
 
     // check mint ib token
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let res = query(deps.as_ref(), env, QueryMsg::State { }).unwrap();
     let state: StateResponse = from_binary(&res).unwrap();
 
     let premium_fraction: Decimal256 = Decimal256::from_ratio((Uint256::from(1100u128) - Uint256::from(1000u128)),Uint256::from(3u128));
@@ -152,12 +241,751 @@ fn funding_rates() {
         who_pays: WhoPays::LONG
     };
     
-    let funding_cumulative: Decimal256 = Decimal256::from_uint256(1_000_000_000u128) + premium_fraction;
+    let funding_cumulative = SignedDecimal256::from(premium_fraction);
     assert_eq!(state.funding_fee, funding);
     assert_eq!(state.funding_premium_cumulative, funding_cumulative);
 }
 
 
+#[test]
+fn funding_history() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("creator", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_100_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // First period ends one funding period after launch
+    let info = mock_info("bank0000", &vec![]);
+    match execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::SettleFunding { }).unwrap_err() {
+        ContractError::NotTime {} => {}
+        _ => panic!("Must return not time error"),
+    }
+
+    let period_nanos = 28_800_000_000_000u64;
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(28_800);
+    execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::SettleFunding { }).unwrap();
+
+    // Settled once per period
+    match execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::SettleFunding { }).unwrap_err() {
+        ContractError::NotTime {} => {}
+        _ => panic!("Must return not time error"),
+    }
+
+    // Two missed periods are settled at once, on the latest boundary
+    let mut late_env = env.clone();
+    late_env.block.time = late_env.block.time.plus_seconds(3 * 28_800 - 3_600);
+    execute(deps.as_mut(), late_env.clone(), info, ExecuteMsg::SettleFunding { }).unwrap();
+
+    let res = query(deps.as_ref(), late_env.clone(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.next_funding_time, env.block.time.plus_seconds(3 * 28_800).seconds());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::FundingHistory { start_after: None, limit: None }).unwrap();
+    let history: FundingHistoryResponse = from_binary(&res).unwrap();
+
+    let first_period = env.block.time.nanos() / period_nanos;
+    let premium_fraction: Decimal256 = Decimal256::from_ratio(Uint256::from(100u128), Uint256::from(3u128));
+    assert_eq!(history.settlements.len(), 2);
+    assert_eq!(history.settlements[0], FundingSettlementResponse {
+        period: first_period,
+        timestamp: env.block.time.nanos(),
+        premium_fraction,
+        funding_rate: premium_fraction / Decimal256::from_uint256(1000u128),
+        mark_price: Decimal256::from_uint256(1100u128),
+        index_price: Decimal256::from_uint256(1000u128),
+        who_pays: WhoPays::LONG
+    });
+    assert_eq!(history.settlements[1].period, first_period + 2);
+    assert_eq!(history.settlements[1].timestamp, late_env.block.time.nanos());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::FundingHistory { start_after: Some(first_period), limit: Some(1) }).unwrap();
+    let history: FundingHistoryResponse = from_binary(&res).unwrap();
+
+    assert_eq!(history.settlements.len(), 1);
+    assert_eq!(history.settlements[0].period, first_period + 2);
+}
+
+#[test]
+fn funding_rate_cap() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("creator", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_100_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::percent(1),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(8 * 60 * 60);
+
+    // Uncapped rate would be (1100 - 1000) / 3 / 1000
+    let info = mock_info("bank0000", &vec![]);
+    let res = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::SettleFunding { }).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "funding_rate_clamped" && attr.value == "true"));
+
+    let res = query(deps.as_ref(), env, QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    let funding: Funding = Funding {
+        amount: Decimal256::percent(1),
+        who_pays: WhoPays::LONG
+    };
+
+    assert_eq!(state.funding_fee, funding);
+    assert_eq!(state.funding_premium_cumulative, SignedDecimal256::from_uint256(10u128.into()));
+}
+
+
+#[test]
+fn twap_mark_price() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Mark price moves from 1000 to 4000, 100 seconds after launch
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+
+    let info = mock_info("bank0000", &vec![]);
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    env.block.time = env.block.time.plus_seconds(100);
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::TwapMarkPrice { interval_seconds: 200 }).unwrap();
+    let twap: Decimal256 = from_binary(&res).unwrap();
+    assert_eq!(twap, Decimal256::from_uint256(2500u128));
+
+    // Interval reaching back before launch only averages over the market lifetime
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::TwapMarkPrice { interval_seconds: 10_000 }).unwrap();
+    let twap: Decimal256 = from_binary(&res).unwrap();
+    assert_eq!(twap, Decimal256::from_uint256(2500u128));
+
+    // Interval after the swap only sees the new price
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::TwapMarkPrice { interval_seconds: 50 }).unwrap();
+    let twap: Decimal256 = from_binary(&res).unwrap();
+    assert_eq!(twap, Decimal256::from_uint256(4000u128));
+}
+
+#[test]
+fn funding_rates_use_twap() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("creator", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Mark price moves halfway through the first period
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(14_400);
+
+    let info = mock_info("bank0000", &vec![]);
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    env.block.time = env.block.time.plus_seconds(14_400);
+
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::SettleFunding { }).unwrap();
+
+    let res = query(deps.as_ref(), env, QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    // TWAP of 2500 against an index of 1000, instead of the instant mark price of 4000
+    let premium_fraction: Decimal256 = Decimal256::from_ratio(Uint256::from(1500u128), Uint256::from(3u128));
+
+    assert_eq!(state.funding_fee, Funding {
+        amount: premium_fraction / Decimal256::from_uint256(1000u128),
+        who_pays: WhoPays::LONG
+    });
+}
+
+#[test]
+fn funding_accrues_continuously() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // No premium while the mark price matches the index of 1000
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+
+    let info = mock_info("bank0000", &vec![]);
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.aggregated_funding, SignedDecimal256::zero());
+
+    // Mark price of 4000 for 4 hours, without any settlement
+    env.block.time = env.block.time.plus_seconds(4 * 60 * 60);
+
+    let res = query(deps.as_ref(), env, QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.funding_premium_cumulative, SignedDecimal256::from_uint256(500u128.into()));
+}
+
+#[test]
+fn funding_skips_oracle_outage() {
+    let mut deps = mock_dependencies(&[]);
+
+    deps.querier.with_mirror_prices(&[
+        (&"mAAPL0000".to_string(), &Decimal::from_ratio(1000u128, 1u128), &1_571_797_419u64)
+    ]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "mAAPL".to_string(),
+        oracle: OracleConfig::MIRROR {
+            oracle_addr: "oracle0000".to_string(),
+            asset_token: "mAAPL0000".to_string()
+        },
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 60,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Oracle price is 4 hours old, the swap moves the mark price to 4000 without accruing
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(4 * 60 * 60);
+
+    let info = mock_info("bank0000", &vec![]);
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let state: State = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.aggregated_funding, SignedDecimal256::zero());
+    assert_eq!(state.last_accrual_time, Uint256::from(env.block.time.nanos()));
+
+    // Oracle recovers 4 hours later, only those 4 hours at 4000 are charged
+    env.block.time = env.block.time.plus_seconds(4 * 60 * 60);
+    deps.querier.with_mirror_prices(&[
+        (&"mAAPL0000".to_string(), &Decimal::from_ratio(1000u128, 1u128), &env.block.time.seconds())
+    ]);
+
+    let res = query(deps.as_ref(), env, QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.funding_premium_cumulative, SignedDecimal256::from_uint256(500u128.into()));
+}
+
+#[test]
+fn simulate_with_fees() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::permille(1),
+        spread_ratio: Decimal256::permille(2),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateIn { quoteAmount: Uint256::from(1_000_000u128), direction: Direction::LONG }).unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+
+    assert_eq!(simulation, SimulationResponse {
+        return_amount: Uint256::from(500u128),
+        toll_fee: Uint256::from(1_000u128),
+        spread_fee: Uint256::from(2_000u128)
+    });
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateOut { baseAmount: Uint256::from(1_000u128), direction: Direction::LONG }).unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+
+    // Fees are charged on the quote amount returned
+    assert_eq!(simulation, SimulationResponse {
+        return_amount: Uint256::from(500_000u128),
+        toll_fee: Uint256::from(500u128),
+        spread_fee: Uint256::from(1_000u128)
+    });
+
+    // Swap reports the fees it charged
+    let info = mock_info("bank0000", &vec![]);
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    assert_eq!(res.attributes[1], attr("toll_fee", "1000"));
+    assert_eq!(res.attributes[2], attr("spread_fee", "2000"));
+}
+
+#[test]
+fn swap_slippage_limits() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("bank0000", &vec![]);
+
+    // Long of 1,000,000 quote returns 500 base, an execution price of 2000 against a mark price of 1000
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: Some(Uint256::from(501u128)), belief_price: None, max_spread: None };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxSlippage {} => {}
+        _ => panic!("Must return max slippage error"),
+    }
+
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: Some(Decimal256::from_uint256(1000u128)), max_spread: Some(Decimal256::percent(50)) };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxSlippage {} => {}
+        _ => panic!("Must return max slippage error"),
+    }
+
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: None, belief_price: None, max_spread: Some(Decimal256::percent(99)) };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxSlippage {} => {}
+        _ => panic!("Must return max slippage error"),
+    }
+
+    // Belief price close to the execution price passes with the default max spread
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(1_000_000u128), base_amount_limit: Some(Uint256::from(500u128)), belief_price: Some(Decimal256::from_str("1995").unwrap()), max_spread: None };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // Short closing 10 base pays 40,816 quote at a 4000 mark price, the limit is the max quote paid
+    let msg = ExecuteMsg::SwapOut { direction: Direction::SHORT, base_asset_amount: Uint256::from(10u128), quote_amount_limit: Some(Uint256::from(40_815u128)), belief_price: None, max_spread: None };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxSlippage {} => {}
+        _ => panic!("Must return max slippage error"),
+    }
+
+    let msg = ExecuteMsg::SwapOut { direction: Direction::SHORT, base_asset_amount: Uint256::from(10u128), quote_amount_limit: Some(Uint256::from(40_817u128)), belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn price_impact_circuit_breaker() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::percent(5),
+        max_block_price_impact: Decimal256::percent(3),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("bank0000", &vec![]);
+
+    // Single swap moving the mark price from 1000 to ~1124
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(60_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxPriceImpact {} => {}
+        _ => panic!("Must return max price impact error"),
+    }
+
+    // Two swaps of ~2% each in the same block exceed the 3% block limit
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(10_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err() {
+        ContractError::MaxPriceImpact {} => {}
+        _ => panic!("Must return max price impact error"),
+    }
+
+    // Next block is measured from the price the previous block closed at
+    let mut env = mock_env();
+    env.block.height += 1;
+    env.block.time = env.block.time.plus_seconds(5);
+
+    execute(deps.as_mut(), env, info, msg).unwrap();
+}
+
+#[test]
+fn open_interest_caps() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::from(15u128),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("bank0000", &vec![]);
+
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(10_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+    // A second long would take the long side above the cap
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxOpenInterest {} => {}
+        _ => panic!("Must return max open interest error"),
+    }
+
+    // Shorts are uncapped
+    let msg = ExecuteMsg::SwapIn { direction: Direction::SHORT, quote_asset_amount: Uint256::from(30_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.total_long_size, Uint256::from(9u128));
+    assert_eq!(state.total_short_size, Uint256::from(31u128));
+    assert_eq!(state.net_position_size, -22i64);
+
+    // Closing longs frees up capacity on the long side
+    let msg = ExecuteMsg::SwapOut { direction: Direction::LONG, base_asset_amount: Uint256::from(5u128), quote_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(state.total_long_size, Uint256::from(4u128));
+    assert_eq!(state.net_position_size, -27i64);
+
+    let msg = ExecuteMsg::SwapIn { direction: Direction::LONG, quote_asset_amount: Uint256::from(5_000u128), base_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn exact_output_swaps() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::zero(),
+        spread_ratio: Decimal256::zero(),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 10 base out of 1000 takes 1_000_000 * 1000 / 990 - 1_000_000 quote, rounded up
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateInExactBase { base_asset_amount: Uint256::from(10u128), direction: Direction::LONG }).unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+    assert_eq!(simulation.return_amount, Uint256::from(10_102u128));
+
+    let info = mock_info("bank0000", &vec![]);
+
+    let msg = ExecuteMsg::SwapInExactBase { direction: Direction::LONG, base_asset_amount: Uint256::from(10u128), max_quote: Some(Uint256::from(10_101u128)) };
+    match execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err() {
+        ContractError::MaxSlippage {} => {}
+        _ => panic!("Must return max slippage error"),
+    }
+
+    let msg = ExecuteMsg::SwapInExactBase { direction: Direction::LONG, base_asset_amount: Uint256::from(10u128), max_quote: Some(Uint256::from(10_102u128)) };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.base_asset_reserve, Uint256::from(990u128));
+    assert_eq!(state.quote_asset_reserve, Uint256::from(1_010_102u128));
+    assert_eq!(state.total_long_size, Uint256::from(10u128));
+
+    // Take out a fixed notional, paid for by selling part of the long
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::SimulateOutExactQuote { quote_asset_amount: Uint256::from(5_000u128), direction: Direction::LONG }).unwrap();
+    let simulation: SimulationResponse = from_binary(&res).unwrap();
+    assert_eq!(simulation.return_amount, Uint256::from(5u128));
+
+    let msg = ExecuteMsg::SwapOutExactQuote { direction: Direction::LONG, quote_asset_amount: Uint256::from(5_000u128), max_base: Some(Uint256::from(5u128)) };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(state.quote_asset_reserve, Uint256::from(1_005_102u128));
+    assert_eq!(state.base_asset_reserve, Uint256::from(995u128));
+    assert_eq!(state.total_long_size, Uint256::from(5u128));
+}
+
+#[test]
+fn quote() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &vec![]);
+
+    let msg = InstantiateMsg {
+        stable_denom: "uusd".to_string(),
+        bank_addr: "bank0000".to_string(),
+        base_denom: "uluna".to_string(),
+        oracle: OracleConfig::NATIVE {},
+        init_quote_reserve: Uint128::from(1_000_000u128),
+        init_base_reserve: Uint128::from(1_000u128),
+        max_oracle_price_age: 0,
+        max_oracle_price_deviation: Decimal256::zero(),
+        snapshot_retention: 86_400,
+        toll_ratio: Decimal256::percent(1),
+        spread_ratio: Decimal256::permille(5),
+        max_trade_price_impact: Decimal256::zero(),
+        max_block_price_impact: Decimal256::zero(),
+        repeg_budget: Uint256::zero(),
+        update_k_budget: Uint256::zero(),
+        max_funding_rate: Decimal256::zero(),
+        max_long_open_interest: Uint256::zero(),
+        max_short_open_interest: Uint256::zero(),
+        curve: None,
+        owner: None
+    };
+
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // 10_000 quote buys 1000 - ceil(1_000_000_000 / 1_010_000) base
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Quote { side: Direction::LONG, amount: Uint256::from(10_000u128), amount_kind: AmountKind::QUOTE }).unwrap();
+    let quote: QuoteResponse = from_binary(&res).unwrap();
+
+    assert_eq!(quote.base_amount, Uint256::from(9u128));
+    assert_eq!(quote.quote_amount, Uint256::from(10_000u128));
+    assert_eq!(quote.average_price, Decimal256::from_ratio(Uint256::from(10_000u128), Uint256::from(9u128)));
+    assert_eq!(quote.mark_price_before, Decimal256::from_uint256(1_000u128));
+    assert_eq!(quote.mark_price_after, Decimal256::from_ratio(Uint256::from(1_010_000u128), Uint256::from(991u128)));
+    assert_eq!(quote.price_impact, (quote.mark_price_after - quote.mark_price_before) / quote.mark_price_before);
+    assert_eq!(quote.toll_fee, Uint256::from(100u128));
+    assert_eq!(quote.spread_fee, Uint256::from(50u128));
+
+    // Selling an exact base amount, as when closing a long
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Quote { side: Direction::SHORT, amount: Uint256::from(10u128), amount_kind: AmountKind::BASE }).unwrap();
+    let quote: QuoteResponse = from_binary(&res).unwrap();
+
+    assert_eq!(quote.base_amount, Uint256::from(10u128));
+    assert_eq!(quote.quote_amount, Uint256::from(9_900u128));
+    assert_eq!(quote.mark_price_after, Decimal256::from_ratio(Uint256::from(990_100u128), Uint256::from(1_010u128)));
+
+    // The quoted trade is the one that executes
+    let info = mock_info("bank0000", &vec![]);
+    let msg = ExecuteMsg::SwapOut { direction: Direction::LONG, base_asset_amount: Uint256::from(10u128), quote_amount_limit: None, belief_price: None, max_spread: None };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State { }).unwrap();
+    let state: StateResponse = from_binary(&res).unwrap();
+    assert_eq!(Decimal256::from_ratio(state.quote_asset_reserve, state.base_asset_reserve), quote.mark_price_after);
+}
+
 // #[test]
 // fn convert() {
 //     let x = Uint256::from(200u128);
@@ -4,6 +4,7 @@ use cosmwasm_std::{Addr, Uint128};
 use cosmwasm_bignumber::{Decimal256,Uint256};
 use cw20::Cw20ReceiveMsg;
 use terraswap::asset::{AssetInfo, Asset};
+use crate::number::SignedDecimal256;
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Direction {
@@ -18,7 +19,9 @@ pub enum Direction {
 pub struct InstantiateMsg {
     pub liquidation_reward: Decimal256,
     pub liquidation_ratio: Decimal256,
-    pub stable_denom: String
+    pub stable_denom: String,
+    pub fee_recipient: String, // Receives toll and spread fees charged by the markets
+    pub funding_bounty: Uint256 // Paid from the fee pool to whoever triggers a funding settlement, the pool is filled by FundFeePool only
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -34,15 +37,25 @@ pub enum ExecuteMsg {
     OpenPosition {
         market_addr: String,
         open_value: Uint256,
-        direction: Direction
+        direction: Direction,
+        base_amount_limit: Option<Uint256>, // Min position size for longs, max position size for shorts
+        belief_price: Option<Decimal256>,
+        max_spread: Option<Decimal256>
     },
     ClosePosition {
-        market_addr: String
+        market_addr: String,
+        quote_amount_limit: Option<Uint256>, // Min value received for longs, max value paid for shorts
+        belief_price: Option<Decimal256>,
+        max_spread: Option<Decimal256>
     },
     Liquidate {
         market_addr: String,
         holder_addr: String
     },
+    SettlePosition { // Closes a position of a shut down market at its settlement price
+        market_addr: String
+    },
+    FundFeePool {}, // Stable sent along is added to the pool keeper bounties are paid from, the only way to fill it as trading fees go to the fee recipient
     UpdateFunding {
         market_addr: String
     },
@@ -63,6 +76,9 @@ pub enum QueryMsg {
     Position {
         market_addr: String,
         user_addr: String
+    },
+    FundingSchedule {
+        market_addr: String
     }
 }
 
@@ -78,19 +94,29 @@ pub struct ConfigResponse {
     pub contract_addr: Addr,
     pub owner_addr: Addr,
     pub stable_denom: String,
+    pub fee_recipient: Addr,
+    pub funding_bounty: Uint256,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct StateResponse {
     pub last_cumulative_funding_fee: u64,
+    pub fee_pool: Uint256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FundingScheduleResponse {
+    pub next_funding_time: u64, // In seconds
+    pub bounty: Uint256 // Paid for triggering the next settlement, capped by the fee pool
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct MarketResponse {
     pub contract_addr: Addr,
-    pub cumulative_funding_premium: Decimal256
+    pub cumulative_funding_premium: SignedDecimal256
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -109,8 +135,7 @@ pub enum Sign {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct FundingResponse {
-    pub amount: Uint256,
-    pub sign: Sign
+    pub amount: SignedDecimal256 // Funding realized by the position, negative if it pays
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -124,5 +149,6 @@ pub struct PositionResponse {
     pub positionSize: Uint256,
     pub direction: Direction,
     pub pnl: i64,
-    pub funding: FundingResponse
+    pub funding: FundingResponse,
+    pub funding_paid: SignedDecimal256 // Funding paid to date, including the open position, negative if received
 }
@@ -0,0 +1,56 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::Addr;
+
+use crate::vamm::InstantiateMsg as VammInstantiateMsg;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InstantiateMsg {
+    pub vamm_code_id: u64, // Code markets are instantiated from
+    pub bank_addr: String // Markets are registered with it on creation
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    UpdateConfig { // Owner only
+        owner: Option<String>,
+        vamm_code_id: Option<u64>
+    },
+    CreateMarket { // Owner only, bank_addr and owner of the template are set by the factory, whose owner is also the migration admin
+        market: Box<VammInstantiateMsg>
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    Markets {
+        start_after: Option<String>, // Market address, markets are ordered by address
+        limit: Option<u32>
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConfigResponse {
+    pub owner_addr: Addr,
+    pub bank_addr: Addr,
+    pub vamm_code_id: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MarketsResponse {
+    pub markets: Vec<MarketResponse>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MarketResponse {
+    pub contract_addr: Addr,
+    pub base_denom: String,
+    pub stable_denom: String
+}
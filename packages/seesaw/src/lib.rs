@@ -1,2 +1,7 @@
 pub mod bank;
-pub mod vamm;
\ No newline at end of file
+pub mod factory;
+pub mod number;
+pub mod vamm;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops;
+use std::str::FromStr;
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::StdError;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{de, ser, Deserialize, Deserializer, Serialize};
+
+// Decimal256 with a sign, for values that move both ways such as cumulative funding.
+// Zero is never negative, so there is a single representation for every value.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub struct SignedDecimal256 {
+    value: Decimal256,
+    negative: bool,
+}
+
+impl SignedDecimal256 {
+    pub fn new(value: Decimal256, negative: bool) -> Self {
+        SignedDecimal256 {
+            value,
+            negative: negative && !value.is_zero(),
+        }
+    }
+
+    pub fn zero() -> Self {
+        SignedDecimal256::new(Decimal256::zero(), false)
+    }
+
+    pub fn from_uint256(value: Uint256) -> Self {
+        SignedDecimal256::new(Decimal256::from_uint256(value), false)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Decimal256 {
+        self.value
+    }
+}
+
+impl From<Decimal256> for SignedDecimal256 {
+    fn from(value: Decimal256) -> Self {
+        SignedDecimal256::new(value, false)
+    }
+}
+
+impl Ord for SignedDecimal256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.value.cmp(&other.value),
+            (true, true) => other.value.cmp(&self.value),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for SignedDecimal256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Neg for SignedDecimal256 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        SignedDecimal256::new(self.value, !self.negative)
+    }
+}
+
+impl ops::Add for SignedDecimal256 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        if self.negative == rhs.negative {
+            return SignedDecimal256::new(self.value + rhs.value, self.negative);
+        }
+
+        // Opposite signs, the larger magnitude keeps its sign
+        if self.value >= rhs.value {
+            SignedDecimal256::new(self.value - rhs.value, self.negative)
+        } else {
+            SignedDecimal256::new(rhs.value - self.value, rhs.negative)
+        }
+    }
+}
+
+impl ops::AddAssign for SignedDecimal256 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl ops::Sub for SignedDecimal256 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl ops::SubAssign for SignedDecimal256 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl ops::Mul<Decimal256> for SignedDecimal256 {
+    type Output = Self;
+
+    fn mul(self, rhs: Decimal256) -> Self {
+        SignedDecimal256::new(self.value * rhs, self.negative)
+    }
+}
+
+impl ops::Mul<Uint256> for SignedDecimal256 {
+    type Output = Self;
+
+    fn mul(self, rhs: Uint256) -> Self {
+        self * Decimal256::from_uint256(rhs)
+    }
+}
+
+impl ops::Div<Decimal256> for SignedDecimal256 {
+    type Output = Self;
+
+    fn div(self, rhs: Decimal256) -> Self {
+        SignedDecimal256::new(self.value / rhs, self.negative)
+    }
+}
+
+impl FromStr for SignedDecimal256 {
+    type Err = StdError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.strip_prefix('-') {
+            Some(value) => Ok(SignedDecimal256::new(Decimal256::from_str(value)?, true)),
+            None => Ok(SignedDecimal256::new(Decimal256::from_str(input)?, false)),
+        }
+    }
+}
+
+impl fmt::Display for SignedDecimal256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            write!(f, "-{}", self.value)
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
+// Serialized as a string, like Decimal256
+impl Serialize for SignedDecimal256 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SignedDecimal256 {
+    fn deserialize<D>(deserializer: D) -> Result<SignedDecimal256, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(SignedDecimal256Visitor)
+    }
+}
+
+struct SignedDecimal256Visitor;
+
+impl<'de> de::Visitor<'de> for SignedDecimal256Visitor {
+    type Value = SignedDecimal256;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("string-encoded signed decimal")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        SignedDecimal256::from_str(v)
+            .map_err(|e| E::custom(format!("Error parsing signed decimal '{}': {}", v, e)))
+    }
+}
+
+impl JsonSchema for SignedDecimal256 {
+    fn schema_name() -> String {
+        "SignedDecimal256".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
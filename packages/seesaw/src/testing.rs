@@ -0,0 +1,48 @@
+use std::str::FromStr;
+
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{from_binary, to_binary};
+
+use crate::number::SignedDecimal256;
+
+#[test]
+fn signed_decimal_arithmetic() {
+    let two = SignedDecimal256::from(Decimal256::from_uint256(2u128));
+    let three = SignedDecimal256::from(Decimal256::from_uint256(3u128));
+
+    assert_eq!(two - three, SignedDecimal256::new(Decimal256::one(), true));
+    assert_eq!(three - two, SignedDecimal256::from(Decimal256::one()));
+    assert_eq!(-two - three, SignedDecimal256::new(Decimal256::from_uint256(5u128), true));
+    assert_eq!(-two + three, SignedDecimal256::from(Decimal256::one()));
+    assert_eq!((two - three) * Uint256::from(4u128), SignedDecimal256::new(Decimal256::from_uint256(4u128), true));
+
+    // Zero has a single representation
+    assert_eq!(two - two, SignedDecimal256::zero());
+    assert_eq!(-SignedDecimal256::zero(), SignedDecimal256::zero());
+    assert!(!(two - two).is_negative());
+}
+
+#[test]
+fn signed_decimal_ordering() {
+    let minus_two = SignedDecimal256::from_str("-2").unwrap();
+    let minus_one = SignedDecimal256::from_str("-1").unwrap();
+    let one = SignedDecimal256::from_str("1").unwrap();
+
+    assert!(minus_two < minus_one);
+    assert!(minus_one < SignedDecimal256::zero());
+    assert!(SignedDecimal256::zero() < one);
+    assert!(minus_two < one);
+}
+
+#[test]
+fn signed_decimal_serialization() {
+    let value = SignedDecimal256::from_str("-1.5").unwrap();
+    assert_eq!(value.to_string(), "-1.5");
+
+    let binary = to_binary(&value).unwrap();
+    assert_eq!(binary.to_vec(), br#""-1.5""#.to_vec());
+    assert_eq!(from_binary::<SignedDecimal256>(&binary).unwrap(), value);
+
+    assert_eq!(SignedDecimal256::from_str("-0").unwrap(), SignedDecimal256::zero());
+    assert!(SignedDecimal256::from_str("--1").is_err());
+}
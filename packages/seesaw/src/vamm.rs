@@ -5,44 +5,158 @@ use cosmwasm_bignumber::{ Decimal256, Uint256 };
 use cw20::Cw20ReceiveMsg;
 use terraswap::asset::{AssetInfo, Asset};
 
-use crate::bank::{ Direction };
+use crate::bank::{ Direction, Sign };
+use crate::number::SignedDecimal256;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
     pub stable_denom: String,
     pub bank_addr: String,
+    pub base_denom: String, // Base asset, a native denom for NATIVE oracles and a symbol otherwise
+    pub oracle: OracleConfig,
     pub init_base_reserve: Uint128,
-    pub init_quote_reserve: Uint128
+    pub init_quote_reserve: Uint128,
+    pub max_oracle_price_age: u64, // In seconds, 0 disables the check, must be 0 for NATIVE which reports no update time
+    pub max_oracle_price_deviation: Decimal256, // Max change between oracle reads, 0 disables the check
+    pub snapshot_retention: u64, // In seconds, must cover the funding period
+    pub toll_ratio: Decimal256,
+    pub spread_ratio: Decimal256,
+    pub max_trade_price_impact: Decimal256, // Max mark price change of a single swap, 0 disables the check
+    pub max_block_price_impact: Decimal256, // Max mark price change within a block, 0 disables the check
+    pub repeg_budget: Uint256, // Max total cost the system takes on from repegs, in quote asset
+    pub update_k_budget: Uint256, // Max total cost the system takes on from changing k, in quote asset
+    pub max_funding_rate: Decimal256, // Max funding rate per funding period, 0 disables the cap
+    pub max_long_open_interest: Uint256, // In base asset, 0 disables the cap
+    pub max_short_open_interest: Uint256, // In base asset, 0 disables the cap
+    pub curve: Option<CurveConfig>, // Defaults to CONSTANT_PRODUCT
+    pub owner: Option<String> // Administers the market, defaults to the sender
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleConfig {
+    NATIVE {}, // Terra treasury exchange rate of base_denom
+    MIRROR { oracle_addr: String, asset_token: String },
+    BAND { oracle_addr: String, base_symbol: String, quote_symbol: String }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveConfig {
+    CONSTANT_PRODUCT {}, // x*y = k
+    STABLE_SWAP { amplification: u64 }, // Flat around the launch price, for pairs trading in a narrow band such as KRW/UST
+    PEGGED { peg_multiplier: Decimal256 } // x*y = k on the quote reserve, each unit of it worth peg_multiplier quote asset
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    SwapIn { direction: Direction, quote_asset_amount: Uint256 }, // Used to open positions
-    SwapOut { direction: Direction, base_asset_amount: Uint256 }, // Used to close position
-    SettleFunding {}
+    SwapIn { // Used to open positions
+        direction: Direction,
+        quote_asset_amount: Uint256,
+        base_amount_limit: Option<Uint256>, // Min base received for longs, max base sold for shorts
+        belief_price: Option<Decimal256>,
+        max_spread: Option<Decimal256>
+    },
+    SwapOut { // Used to close position
+        direction: Direction,
+        base_asset_amount: Uint256,
+        quote_amount_limit: Option<Uint256>, // Min quote received for longs, max quote paid for shorts
+        belief_price: Option<Decimal256>,
+        max_spread: Option<Decimal256>
+    },
+    SwapInExactBase { // Opens a position of an exact size
+        direction: Direction,
+        base_asset_amount: Uint256,
+        max_quote: Option<Uint256> // Max quote paid for longs, min quote received for shorts
+    },
+    SwapOutExactQuote { // Partially closes a position for an exact notional
+        direction: Direction,
+        quote_asset_amount: Uint256,
+        max_base: Option<Uint256> // Max base sold for longs, min base bought back for shorts
+    },
+    SettleFunding {},
+    Repeg {}, // Owner only, moves the mark price to the oracle price
+    UpdateK { scale: Decimal256 }, // Owner only, multiplies both reserves by scale keeping the mark price
+    Shutdown { price_source: SettlementSource }, // Owner only, freezes the market at a settlement price
+    ResetOracle {} // Owner only, accepts the current oracle price as the reference of the deviation guard, funding accrual stays paused until then
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementSource {
+    ORACLE, // Current oracle price
+    TWAP // Mark price averaged over the last funding period, for when the oracle is gone
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     // GetCount returns the current count as a json-encoded number
-    SimulateIn { quoteAmount: Uint256, direction: Direction }, // base price from quote price
-    SimulateOut { baseAmount: Uint256, direction: Direction }, // Base amount to Long quote amount
+    SimulateIn { quoteAmount: Uint256, direction: Direction }, // base price from quote price, returns SimulationResponse
+    SimulateOut { baseAmount: Uint256, direction: Direction }, // Base amount to Long quote amount, returns SimulationResponse
+    SimulateInExactBase { base_asset_amount: Uint256, direction: Direction }, // Quote needed to open an exact size, returns SimulationResponse
+    SimulateOutExactQuote { quote_asset_amount: Uint256, direction: Direction }, // Base closed for an exact notional, returns SimulationResponse
+    Quote { side: Direction, amount: Uint256, amount_kind: AmountKind }, // LONG buys base from the market, SHORT sells base to it
+    Config {},
     OraclePrice {},
     MarketPrice {}, // Price of assets in market
     State {},
     MarketInfo {},
-    MarketSnapshots {}
+    MarketSnapshots {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        start_time: Option<u64>, // Nanoseconds, same as snapshot timestamps
+        end_time: Option<u64>
+    },
+    OracleStatus {},
+    TwapMarkPrice { interval_seconds: u64 }, // Time weighted mark price over the last interval
+    Candles { resolution_seconds: u64, start: u64, end: u64 }, // Seconds, buckets without swaps are left out
+    FundingHistory { start_after: Option<u64>, limit: Option<u32> }, // Settlements in period order
+    SimulateRepeg {},
+    SimulateUpdateK { scale: Decimal256 }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountKind {
+    BASE, // Exact base traded
+    QUOTE // Exact quote traded
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct ConfigResponse {
     pub contract_addr: Addr,
+    pub owner_addr: Addr,
     pub bank_addr: Addr,
     pub stable_denom: String,
+    pub base_denom: String,
+    pub oracle: OracleConfig,
+    pub curve: CurveConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SimulationResponse {
+    pub return_amount: Uint256, // Base amount for SimulateIn and SimulateOutExactQuote, quote amount otherwise
+    pub toll_fee: Uint256, // In quote asset
+    pub spread_fee: Uint256 // In quote asset
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct QuoteResponse {
+    pub base_amount: Uint256,
+    pub quote_amount: Uint256,
+    pub average_price: Decimal256, // Quote paid or received per base
+    pub mark_price_before: Decimal256,
+    pub mark_price_after: Decimal256,
+    pub price_impact: Decimal256, // Relative mark price change
+    pub toll_fee: Uint256, // In quote asset
+    pub spread_fee: Uint256, // In quote asset
+    pub funding_rate: Funding // Rate of the last funding settlement
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -64,10 +178,142 @@ pub struct Funding {
 pub struct StateResponse {
     pub base_asset_reserve: Uint256,
     pub quote_asset_reserve: Uint256,
-    pub funding_premium_cumulative: Decimal256,
+    pub funding_premium_cumulative: SignedDecimal256,
     pub funding_fee: Funding,
     pub market_price: Decimal256,
-    pub underlying_price: Decimal256
+    pub underlying_price: Decimal256,
+    pub total_long_size: Uint256,
+    pub total_short_size: Uint256,
+    pub net_position_size: i64, // Positive if traders are net long
+    pub settlement_price: Option<Decimal256>, // Set once the market is shut down
+    pub next_funding_time: u64 // In seconds, SettleFunding is rejected before it
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleStatus {
+    Valid,
+    Stale, // Older than max_oracle_price_age
+    Deviation // Moved more than max_oracle_price_deviation since the last funding settlement
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleSource {
+    NATIVE,
+    MIRROR,
+    BAND
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OraclePriceResponse {
+    pub price: Decimal256,
+    pub source: OracleSource,
+    pub last_updated: u64 // In seconds
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MarketPriceResponse {
+    pub mark_price: Decimal256,
+    pub twap_mark_price: Decimal256, // Over the last funding period
+    pub index_price: Decimal256,
+    pub spread: SignedDecimal256 // (mark - index) / index
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MarketInfoResponse {
+    pub base_symbol: String,
+    pub stable_denom: String,
+    pub oracle_source: OracleSource,
+    pub funding_period: u64, // In seconds
+    pub toll_ratio: Decimal256,
+    pub spread_ratio: Decimal256,
+    pub init_base_reserve: Uint256,
+    pub init_quote_reserve: Uint256
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OracleStatusResponse {
+    pub status: OracleStatus,
+    pub price: Decimal256,
+    pub last_updated: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SnapshotResponse {
+    pub index: u64,
+    pub base_asset_reserve: Uint256,
+    pub quote_asset_reserve: Uint256,
+    pub base_delta: i64,
+    pub timestamp: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MarketSnapshotsResponse {
+    pub snapshots: Vec<SnapshotResponse>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CandleResponse {
+    pub start: u64, // Bucket start, in seconds
+    pub open: Decimal256,
+    pub high: Decimal256,
+    pub low: Decimal256,
+    pub close: Decimal256,
+    pub base_volume: Uint256,
+    pub quote_volume: Uint256
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CandlesResponse {
+    pub candles: Vec<CandleResponse>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FundingSettlementResponse {
+    pub period: u64,
+    pub timestamp: u64,
+    pub premium_fraction: Decimal256,
+    pub funding_rate: Decimal256,
+    pub mark_price: Decimal256,
+    pub index_price: Decimal256,
+    pub who_pays: WhoPays
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FundingHistoryResponse {
+    pub settlements: Vec<FundingSettlementResponse>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RepegResponse {
+    pub base_asset_reserve: Uint256,
+    pub quote_asset_reserve: Uint256, // Quote reserve after the repeg
+    pub mark_price_before: Decimal256,
+    pub mark_price_after: Decimal256,
+    pub cost: Uint256, // In quote asset
+    pub cost_sign: Sign // Positive if the system pays, negative if it gains
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UpdateKResponse {
+    pub base_asset_reserve: Uint256, // Base reserve after the change
+    pub quote_asset_reserve: Uint256, // Quote reserve after the change
+    pub mark_price: Decimal256,
+    pub cost: Uint256, // In quote asset
+    pub cost_sign: Sign // Positive if the system pays, negative if it gains
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]